@@ -0,0 +1,163 @@
+use crate::fetch::FetchedTransaction;
+use solana_sdk::pubkey::Pubkey;
+
+// 单个账户的 SOL 余额变化，手续费已经从付款人那里剔除
+#[derive(Clone, Debug, PartialEq)]
+pub struct BalanceChange {
+    pub account: Pubkey,
+    pub pre: u64,
+    pub post: u64,
+    pub delta: i64,
+}
+
+// 配对出来的一笔资金流向：from 转了 lamports 给 to
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transfer {
+    pub from: Pubkey,
+    pub to: Pubkey,
+    pub lamports: u64,
+}
+
+// 一笔交易里所有账户 (静态 + 查找表) 的余额变化
+#[derive(Clone, Debug)]
+pub struct BalanceDeltas {
+    pub fee_payer: Pubkey,
+    pub fee: u64,
+    pub changes: Vec<BalanceChange>,
+}
+
+impl BalanceDeltas {
+    pub fn from_transaction(tx: &FetchedTransaction) -> Self {
+        let meta = &tx.meta;
+        let fee_payer = tx.account_keys.first().copied().unwrap_or_default();
+
+        let changes = tx
+            .account_keys
+            .iter()
+            .zip(meta.pre_balances.iter().zip(&meta.post_balances))
+            .enumerate()
+            .filter_map(|(i, (account, (&pre, &post)))| {
+                let mut delta = post as i64 - pre as i64;
+                // 手续费不算资金流动，把它还给付款人
+                if i == 0 {
+                    delta += meta.fee as i64;
+                }
+                (delta != 0).then_some(BalanceChange { account: *account, pre, post, delta })
+            })
+            .collect();
+
+        Self { fee_payer, fee: meta.fee, changes }
+    }
+
+    // 所有转出方合计流出的金额
+    pub fn total_sent(&self) -> u64 {
+        self.changes.iter().filter(|c| c.delta < 0).map(|c| c.delta.unsigned_abs()).sum()
+    }
+
+    pub fn transfers(&self) -> Vec<Transfer> {
+        pair_flows(self.changes.iter().map(|c| (c.account, c.delta as i128)))
+            .into_iter()
+            .map(|(from, to, amount)| Transfer { from, to, lamports: amount as u64 })
+            .collect()
+    }
+}
+
+// 贪心配对：转出最多的先配收到最多的，直到一方耗尽。SOL 和 SPL 代币共用
+pub fn pair_flows<K: Copy>(deltas: impl IntoIterator<Item = (K, i128)>) -> Vec<(K, K, u128)> {
+    let mut senders = Vec::new();
+    let mut receivers = Vec::new();
+    for (key, delta) in deltas {
+        if delta < 0 {
+            senders.push((key, delta.unsigned_abs()));
+        } else if delta > 0 {
+            receivers.push((key, delta as u128));
+        }
+    }
+    senders.sort_by_key(|s| std::cmp::Reverse(s.1));
+    receivers.sort_by_key(|r| std::cmp::Reverse(r.1));
+
+    let mut flows = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < senders.len() && j < receivers.len() {
+        let amount = senders[i].1.min(receivers[j].1);
+        flows.push((senders[i].0, receivers[j].0, amount));
+        senders[i].1 -= amount;
+        receivers[j].1 -= amount;
+        if senders[i].1 == 0 { i += 1; }
+        if receivers[j].1 == 0 { j += 1; }
+    }
+    flows
+}
+
+// 地址太长，消息里只显示头尾
+pub fn short_address(address: &Pubkey) -> String {
+    let s = address.to_string();
+    format!("{}…{}", &s[..4], &s[s.len() - 4..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn load(json: &str) -> FetchedTransaction {
+        FetchedTransaction::parse(serde_json::from_str(json).unwrap()).unwrap()
+    }
+
+    fn key(s: &str) -> Pubkey {
+        Pubkey::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn fee_is_separated_from_movement() {
+        let deltas = BalanceDeltas::from_transaction(&load(include_str!("../tests/fixtures/legacy_transfer.json")));
+
+        assert_eq!(deltas.fee, 5000);
+        assert_eq!(deltas.fee_payer, key("4Ypn3UXUUstkpPhJ7ZpyxACjThWPNyKPMwcn6FdeJsx2"));
+        assert_eq!(deltas.total_sent(), 250_000_000_000);
+        assert_eq!(
+            deltas.transfers(),
+            vec![Transfer {
+                from: key("4Ypn3UXUUstkpPhJ7ZpyxACjThWPNyKPMwcn6FdeJsx2"),
+                to: key("DTKntNWAAidsxiD8QkMscLbGJxTEayn6ecLaVt635Tzz"),
+                lamports: 250_000_000_000,
+            }]
+        );
+    }
+
+    #[test]
+    fn lookup_table_receiver_is_included() {
+        let deltas = BalanceDeltas::from_transaction(&load(include_str!("../tests/fixtures/v0_lookup_transfer.json")));
+
+        let transfers = deltas.transfers();
+        assert_eq!(transfers.len(), 1);
+        assert_eq!(transfers[0].to, key("5maYy2W1MwSBNXeJseBzWYAF67wxGEXS3gcURwKq9Ffp"));
+        assert_eq!(transfers[0].lamports, 1_200_000_000_000);
+    }
+
+    #[test]
+    fn non_signer_vault_is_paired_with_multiple_receivers() {
+        let payer = Pubkey::new_unique();
+        let vault = Pubkey::new_unique();
+        let a = Pubkey::new_unique();
+        let b = Pubkey::new_unique();
+        let deltas = BalanceDeltas {
+            fee_payer: payer,
+            fee: 5000,
+            changes: vec![
+                BalanceChange { account: vault, pre: 1_000, post: 100, delta: -900 },
+                BalanceChange { account: a, pre: 0, post: 600, delta: 600 },
+                BalanceChange { account: b, pre: 0, post: 300, delta: 300 },
+            ],
+        };
+
+        assert_eq!(deltas.total_sent(), 900);
+        assert_eq!(
+            deltas.transfers(),
+            vec![
+                Transfer { from: vault, to: a, lamports: 600 },
+                Transfer { from: vault, to: b, lamports: 300 },
+            ]
+        );
+    }
+}
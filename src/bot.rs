@@ -0,0 +1,339 @@
+use crate::backoff::Backoff;
+use crate::config::{Config, WalletThreshold};
+use crate::sinks::telegram::{build_client, TelegramOutbox};
+use crate::watchlist::Watchlist;
+use anyhow::{anyhow, bail, Context};
+use serde::Deserialize;
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+const HELP: &str = "可用命令:
+/watch &lt;地址&gt; &lt;SOL&gt; - 关注钱包，单笔余额变化超过阈值就报警
+/unwatch &lt;地址&gt; - 取消关注
+/list - 查看关注的钱包
+/threshold [SOL] - 查看或修改全局 SOL 阈值
+/mute &lt;30m|1h|1d|off&gt; - 暂停报警一段时间
+/status - 运行状态";
+
+// getUpdates 的响应，只解析用得到的字段
+#[derive(Debug, Deserialize)]
+struct UpdatesResponse {
+    ok: bool,
+    #[serde(default)]
+    result: Vec<Update>,
+    description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Update {
+    update_id: i64,
+    message: Option<Message>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Message {
+    chat: Chat,
+    from: Option<User>,
+    text: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Chat {
+    id: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct User {
+    id: i64,
+}
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Watch(WalletThreshold),
+    Unwatch(Pubkey),
+    List,
+    Threshold(Option<f64>),
+    // None 表示取消静音
+    Mute(Option<Duration>),
+    Status,
+    Help,
+}
+
+impl Command {
+    fn parse(text: &str) -> anyhow::Result<Self> {
+        let mut args = text.split_whitespace();
+        // 群里的命令会带上 bot 的用户名: /watch@my_bot
+        let name = args.next().unwrap_or_default().split('@').next().unwrap_or_default();
+        let args: Vec<&str> = args.collect();
+
+        let command = match (name, args.as_slice()) {
+            ("/watch", [address, min_sol]) => Command::Watch(WalletThreshold {
+                address: parse_address(address)?,
+                min_sol: parse_sol(min_sol)?,
+            }),
+            ("/watch", _) => bail!("用法: /watch &lt;地址&gt; &lt;SOL&gt;"),
+            ("/unwatch", [address]) => Command::Unwatch(parse_address(address)?),
+            ("/unwatch", _) => bail!("用法: /unwatch &lt;地址&gt;"),
+            ("/list", []) => Command::List,
+            ("/threshold", []) => Command::Threshold(None),
+            ("/threshold", [sol]) => Command::Threshold(Some(parse_sol(sol)?)),
+            ("/mute", ["off"]) => Command::Mute(None),
+            ("/mute", [duration]) => Command::Mute(Some(parse_duration(duration)?)),
+            ("/mute", _) => bail!("用法: /mute &lt;30m|1h|1d|off&gt;"),
+            ("/status", []) => Command::Status,
+            _ => Command::Help,
+        };
+        Ok(command)
+    }
+}
+
+fn parse_address(text: &str) -> anyhow::Result<Pubkey> {
+    Pubkey::from_str(text).map_err(|_| anyhow!("不是合法的地址: {}", escape(text)))
+}
+
+fn parse_sol(text: &str) -> anyhow::Result<f64> {
+    match text.parse::<f64>() {
+        Ok(sol) if sol > 0.0 && sol.is_finite() => Ok(sol),
+        _ => bail!("阈值必须是大于 0 的数字: {}", escape(text)),
+    }
+}
+
+// 静音最长一年，再长的大概率是手误
+const MAX_DURATION: Duration = Duration::from_secs(365 * 86400);
+
+// 30s / 15m / 1h / 2d
+fn parse_duration(text: &str) -> anyhow::Result<Duration> {
+    let unit = text.chars().last().unwrap_or_default();
+    let seconds = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 3600,
+        'd' => 86400,
+        _ => bail!("时长格式错误: {} (例如 30m / 1h / 1d)", escape(text)),
+    };
+    let number: u64 = text[..text.len() - unit.len_utf8()].parse().map_err(|_| anyhow!("时长格式错误: {}", escape(text)))?;
+    match number.checked_mul(seconds).map(Duration::from_secs) {
+        Some(duration) if duration <= MAX_DURATION => Ok(duration),
+        _ => bail!("时长太长: {} (最长 365d)", escape(text)),
+    }
+}
+
+// 回复用 HTML 格式，用户输入原样回显时要转义
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+// --- Telegram 命令 bot：长轮询 getUpdates，修改关注列表后立刻生效 ---
+pub struct Bot {
+    client: reqwest::Client,
+    api_url: String,
+    poll_timeout: Duration,
+    allowed_users: Vec<i64>,
+    outbox: TelegramOutbox,
+    watchlist: Arc<Watchlist>,
+    sinks: usize,
+    started: Instant,
+}
+
+impl Bot {
+    pub fn new(config: &Config, outbox: TelegramOutbox, watchlist: Arc<Watchlist>, sinks: usize) -> anyhow::Result<Self> {
+        let token = config.telegram.token.as_deref().context("telegram.bot 需要 telegram.token")?;
+        Ok(Self {
+            client: build_client(&config.telegram.proxy)?,
+            api_url: format!("{}/bot{}", config.telegram.api_url.trim_end_matches('/'), token),
+            poll_timeout: Duration::from_secs(config.telegram.bot.poll_timeout_secs),
+            allowed_users: config.telegram.bot.allowed_users.clone(),
+            outbox,
+            watchlist,
+            sinks,
+            started: Instant::now(),
+        })
+    }
+
+    pub async fn run(self) {
+        let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(60));
+        let mut offset = 0;
+        info!("🤖 Telegram bot 已启动，等待命令...");
+
+        loop {
+            match self.poll(offset).await {
+                Ok(updates) => {
+                    backoff.reset();
+                    for update in updates {
+                        // offset 往前推，已经处理过的更新 Telegram 就不会再发
+                        offset = update.update_id + 1;
+                        if let Some(message) = update.message {
+                            self.handle(message).await;
+                        }
+                    }
+                }
+                Err(e) => {
+                    let delay = backoff.next_delay();
+                    warn!(error = format!("{:#}", e), delay_secs = delay.as_secs(), "⚠️ getUpdates 失败，稍后重试");
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+    }
+
+    async fn poll(&self, offset: i64) -> anyhow::Result<Vec<Update>> {
+        let params = serde_json::json!({
+            "offset": offset,
+            "timeout": self.poll_timeout.as_secs(),
+            "allowed_updates": ["message"],
+        });
+        let res = self
+            .client
+            .post(format!("{}/getUpdates", self.api_url))
+            .json(&params)
+            // 长轮询本身就要挂 timeout 秒，请求超时要留点余量
+            .timeout(self.poll_timeout + Duration::from_secs(10))
+            .send()
+            .await?;
+        let status = res.status();
+        let body: UpdatesResponse = res.json().await.with_context(|| format!("Status {}", status))?;
+        if !body.ok {
+            bail!("Status {}: {}", status, body.description.unwrap_or_default());
+        }
+        Ok(body.result)
+    }
+
+    async fn handle(&self, message: Message) {
+        let Some(text) = message.text else { return };
+        if !text.starts_with('/') {
+            return;
+        }
+
+        let user = message.from.map(|u| u.id);
+        let reply = if user.is_some_and(|id| self.allowed_users.contains(&id)) {
+            info!(?user, command = %text, "🤖 收到命令");
+            let result = match Command::parse(&text) {
+                Ok(command) => self.execute(command).await,
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(reply) => reply,
+                Err(e) => format!("❌ {:#}", e),
+            }
+        } else {
+            warn!(?user, command = %text, "⛔ 拒绝未授权用户的命令");
+            "⛔ 你没有权限使用这个 bot".to_string()
+        };
+
+        if let Err(e) = self.outbox.send(&message.chat.id.to_string(), reply).await {
+            warn!(error = format!("{:#}", e), "⚠️ 回复命令失败");
+        }
+    }
+
+    async fn execute(&self, command: Command) -> anyhow::Result<String> {
+        let reply = match command {
+            Command::Watch(wallet) => {
+                let reply = format!("👀 已关注 <code>{}</code>，单笔变化 &gt; {} SOL 时报警", wallet.address, wallet.min_sol);
+                self.watchlist.update(|s| {
+                    s.wallets.retain(|w| w.address != wallet.address);
+                    s.wallets.push(wallet);
+                }).await?;
+                reply
+            }
+            Command::Unwatch(address) => {
+                let removed = self.watchlist.update(|s| {
+                    let before = s.wallets.len();
+                    s.wallets.retain(|w| w.address != address);
+                    s.wallets.len() != before
+                }).await?;
+                if removed {
+                    format!("🙈 已取消关注 <code>{}</code>", address)
+                } else if self.watchlist.thresholds().wallet(&address).is_some() {
+                    format!("⚠️ <code>{}</code> 写在配置文件里，需要修改配置文件", address)
+                } else {
+                    format!("⚠️ <code>{}</code> 不在关注列表里", address)
+                }
+            }
+            Command::List => {
+                let added = self.watchlist.snapshot().wallets;
+                let wallets = self.watchlist.thresholds().wallets;
+                if wallets.is_empty() {
+                    "📭 关注列表为空，用 /watch 添加".to_string()
+                } else {
+                    let mut lines = vec![format!("👀 关注中的钱包 ({} 个):", wallets.len())];
+                    for wallet in &wallets {
+                        let source = if added.contains(wallet) { "" } else { " (配置文件)" };
+                        lines.push(format!("• <code>{}</code> &gt; {} SOL{}", wallet.address, wallet.min_sol, source));
+                    }
+                    lines.join("\n")
+                }
+            }
+            Command::Threshold(None) => format!("📏 当前 SOL 阈值: {} SOL", self.watchlist.thresholds().sol),
+            Command::Threshold(Some(sol)) => {
+                self.watchlist.update(|s| s.sol_threshold = Some(sol)).await?;
+                format!("📏 SOL 阈值已改为 {} SOL", sol)
+            }
+            Command::Mute(Some(duration)) => {
+                let until = i64::try_from(duration.as_secs())
+                    .ok()
+                    .and_then(chrono::Duration::try_seconds)
+                    .and_then(|d| chrono::Utc::now().checked_add_signed(d))
+                    .ok_or_else(|| anyhow!("时长太长: {}s", duration.as_secs()))?;
+                self.watchlist.update(|s| s.muted_until = Some(until.timestamp())).await?;
+                format!("🔕 已静音到 {}", until.format("%Y-%m-%d %H:%M UTC"))
+            }
+            Command::Mute(None) => {
+                self.watchlist.update(|s| s.muted_until = None).await?;
+                "🔔 已取消静音".to_string()
+            }
+            Command::Status => self.status(),
+            Command::Help => HELP.to_string(),
+        };
+        Ok(reply)
+    }
+
+    fn status(&self) -> String {
+        let uptime = self.started.elapsed().as_secs();
+        let thresholds = self.watchlist.thresholds();
+        let mute = match self.watchlist.snapshot().muted_until {
+            Some(until) if self.watchlist.is_muted() => {
+                let until = chrono::DateTime::from_timestamp(until, 0).unwrap_or_default();
+                format!("静音到 {}", until.format("%Y-%m-%d %H:%M UTC"))
+            }
+            _ => "正常报警".to_string(),
+        };
+        format!(
+            "📊 <b>运行状态</b>\n⏱️ 已运行: {}h{}m\n📏 SOL 阈值: {} SOL\n👀 关注钱包: {} 个\n📣 报警通道: {} 个\n🔔 {}",
+            uptime / 3600,
+            uptime % 3600 / 60,
+            thresholds.sol,
+            thresholds.wallets.len(),
+            self.sinks,
+            mute
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_commands() {
+        let address = "DTKntNWAAidsxiD8QkMscLbGJxTEayn6ecLaVt635Tzz";
+        assert_eq!(
+            Command::parse(&format!("/watch@whale_bot {} 500", address)).unwrap(),
+            Command::Watch(WalletThreshold { address: Pubkey::from_str(address).unwrap(), min_sol: 500.0 })
+        );
+        assert_eq!(Command::parse("/threshold 2.5").unwrap(), Command::Threshold(Some(2.5)));
+        assert_eq!(Command::parse("/mute 1h").unwrap(), Command::Mute(Some(Duration::from_secs(3600))));
+        assert_eq!(Command::parse("/mute off").unwrap(), Command::Mute(None));
+        assert_eq!(Command::parse("/start").unwrap(), Command::Help);
+
+        assert!(Command::parse("/watch not-an-address 500").is_err());
+        assert!(Command::parse(&format!("/watch {} -1", address)).is_err());
+        assert!(Command::parse("/mute 1w").is_err());
+        assert_eq!(Command::parse("/mute 365d").unwrap(), Command::Mute(Some(MAX_DURATION)));
+        assert!(Command::parse("/mute 366d").is_err());
+        assert!(Command::parse("/mute 99999999999999d").unwrap_err().to_string().contains("太长"));
+        assert!(Command::parse("/threshold <b>").unwrap_err().to_string().contains("&lt;b&gt;"));
+    }
+}
@@ -0,0 +1,141 @@
+use crate::config::DedupConfig;
+use anyhow::Context;
+use std::collections::{HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+
+// --- 见过的签名：按时间过期 + 容量上限，可选落盘 ---
+pub struct SeenSignatures {
+    ttl: i64,
+    capacity: usize,
+    path: Option<PathBuf>,
+    set: HashSet<String>,
+    // (签名, 第一次见到的 unix 时间戳)，按时间先后排列
+    order: VecDeque<(String, i64)>,
+    // 上次保存后有没有新签名
+    dirty: bool,
+}
+
+impl SeenSignatures {
+    pub fn load(config: &DedupConfig) -> anyhow::Result<Self> {
+        let mut seen = Self::new(config.ttl_secs as i64, config.capacity, config.path.clone());
+        let Some(path) = &config.path else { return Ok(seen) };
+
+        let mut entries: Vec<(String, i64)> = match std::fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text).with_context(|| format!("去重文件 {} 格式错误", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e).with_context(|| format!("无法读取去重文件 {}", path.display())),
+        };
+        entries.sort_by_key(|(_, seen_at)| *seen_at);
+        let now = chrono::Utc::now().timestamp();
+        for (signature, seen_at) in entries {
+            seen.insert_at(&signature, seen_at);
+        }
+        seen.expire(now);
+        seen.dirty = false;
+        Ok(seen)
+    }
+
+    // 只在内存里，不读也不写 path
+    pub fn in_memory(config: &DedupConfig) -> Self {
+        Self::new(config.ttl_secs as i64, config.capacity, None)
+    }
+
+    fn new(ttl: i64, capacity: usize, path: Option<PathBuf>) -> Self {
+        Self { ttl, capacity, path, set: HashSet::new(), order: VecDeque::new(), dirty: false }
+    }
+
+    // 第一次见到 (或者上次见到已经过期) 时返回 true
+    pub fn insert(&mut self, signature: &str) -> bool {
+        self.insert_at(signature, chrono::Utc::now().timestamp())
+    }
+
+    fn insert_at(&mut self, signature: &str, now: i64) -> bool {
+        self.expire(now);
+        if !self.set.insert(signature.to_string()) {
+            return false;
+        }
+        self.order.push_back((signature.to_string(), now));
+        if self.order.len() > self.capacity
+            && let Some((oldest, _)) = self.order.pop_front()
+        {
+            self.set.remove(&oldest);
+        }
+        self.dirty = true;
+        true
+    }
+
+    fn expire(&mut self, now: i64) {
+        while let Some((_, seen_at)) = self.order.front()
+            && seen_at + self.ttl <= now
+        {
+            let (signature, _) = self.order.pop_front().expect("front 存在");
+            self.set.remove(&signature);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    // 没有配置 path 或者没有变化时什么都不做
+    pub fn save(&mut self) -> anyhow::Result<()> {
+        let Some(path) = &self.path else { return Ok(()) };
+        if !self.dirty {
+            return Ok(());
+        }
+        save(path, &self.order)?;
+        self.dirty = false;
+        Ok(())
+    }
+}
+
+// 先写临时文件再改名，避免写到一半崩溃把文件弄坏
+fn save(path: &Path, entries: &VecDeque<(String, i64)>) -> anyhow::Result<()> {
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, serde_json::to_vec(entries)?).with_context(|| format!("无法写入去重文件 {}", tmp.display()))?;
+    std::fs::rename(&tmp, path).with_context(|| format!("无法写入去重文件 {}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expires_by_time_and_capacity() {
+        let mut seen = SeenSignatures::new(60, 2, None);
+        assert!(seen.insert_at("a", 1000));
+        assert!(!seen.insert_at("a", 1010));
+        assert!(seen.insert_at("b", 1020));
+
+        // 容量只有 2，c 进来把最早的 a 挤掉
+        assert!(seen.insert_at("c", 1030));
+        assert!(seen.insert_at("a", 1031));
+        assert_eq!(seen.len(), 2);
+
+        // c 是 1030 见到的，60 秒后过期，1031 的 a 还在
+        assert!(seen.insert_at("c", 1090));
+        assert!(!seen.insert_at("a", 1090));
+    }
+
+    #[test]
+    fn survives_restart() {
+        let path = std::env::temp_dir().join(format!("whale-seen-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let config = DedupConfig { path: Some(path.clone()), ..Default::default() };
+
+        let mut seen = SeenSignatures::load(&config).unwrap();
+        assert!(seen.insert("sig"));
+        seen.save().unwrap();
+        assert_eq!(SeenSignatures::load(&config).unwrap().len(), 1);
+
+        // 很久以前见过的，读回来时应该已经过期
+        let now = chrono::Utc::now().timestamp();
+        let entries = VecDeque::from([("old".to_string(), now - 3600), ("sig".to_string(), now)]);
+        save(&path, &entries).unwrap();
+        let mut restarted = SeenSignatures::load(&config).unwrap();
+        assert!(!restarted.insert("sig"));
+        assert!(restarted.insert("old"));
+        let _ = std::fs::remove_file(&path);
+    }
+}
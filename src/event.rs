@@ -0,0 +1,261 @@
+use crate::balance::{BalanceDeltas, Transfer};
+use crate::config::ThresholdConfig;
+use crate::fetch::FetchedTransaction;
+use crate::token::{self, TokenTransfer};
+use serde::{Deserialize, Serialize};
+use solana_sdk::native_token::lamports_to_sol;
+use solana_sdk::pubkey::Pubkey;
+use solana_transaction_status::option_serializer::OptionSerializer;
+
+// 金额达到阈值的多少倍时升级严重程度
+const WARNING_RATIO: f64 = 10.0;
+const CRITICAL_RATIO: f64 = 100.0;
+
+// 一次巨鲸事件：所有报警通道收到的都是这个结构，各自决定怎么渲染
+#[derive(Clone, Debug)]
+pub struct WhaleEvent {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub fee: u64,
+    pub fee_payer: Pubkey,
+    pub severity: Severity,
+    pub status: TxStatus,
+    // 超过 SOL 阈值时才有
+    pub sol: Option<SolMovement>,
+    // 每个超过阈值的 mint 一条
+    pub tokens: Vec<TokenMovement>,
+    // 交易里 Memo 程序写的备注，任何人都能随便填，渲染时必须转义
+    pub memo: Option<String>,
+    // 哪一路订阅发现的 (subscription.feeds 的 name 或 "wallet")，detect 之后由调用方填
+    pub feed: String,
+    // 触发了哪些阈值: sol / wallet / token:<symbol 或 mint>，用于统计
+    pub rules: Vec<String>,
+}
+
+// 按金额超过阈值的倍数分级，报警通道可以据此路由
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Critical,
+}
+
+impl Severity {
+    fn from_ratio(ratio: f64) -> Self {
+        if ratio >= CRITICAL_RATIO {
+            Severity::Critical
+        } else if ratio >= WARNING_RATIO {
+            Severity::Warning
+        } else {
+            Severity::Info
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Severity::Info => "INFO",
+            Severity::Warning => "WARNING",
+            Severity::Critical => "CRITICAL",
+        }
+    }
+}
+
+// 交易的确认状态：先按 confirmed 报警，之后再更新成最终确认或被丢弃
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TxStatus {
+    Confirmed,
+    Finalized,
+    // 一直等不到 finalized，大概率是所在的分叉被放弃了
+    Dropped,
+}
+
+impl TxStatus {
+    pub fn badge(self) -> &'static str {
+        match self {
+            TxStatus::Confirmed => "🟡 已确认",
+            TxStatus::Finalized => "🟢 已最终确认",
+            TxStatus::Dropped => "🔴 已丢弃 (未上链)",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SolMovement {
+    pub lamports: u64,
+    pub transfers: Vec<Transfer>,
+}
+
+impl SolMovement {
+    pub fn amount(&self) -> f64 {
+        lamports_to_sol(self.lamports)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TokenMovement {
+    pub mint: String,
+    pub symbol: Option<String>,
+    // UI 金额，已经除过 decimals
+    pub amount: f64,
+    pub transfers: Vec<TokenTransfer>,
+}
+
+impl WhaleEvent {
+    // 和阈值比较，SOL 和代币都没超过时返回 None
+    pub fn detect(tx: &FetchedTransaction, thresholds: &ThresholdConfig) -> Option<Self> {
+        let deltas = BalanceDeltas::from_transaction(tx);
+
+        let lamports = deltas.total_sent();
+        let sent = lamports_to_sol(lamports);
+        let mut sol_ratio = sent / thresholds.sol;
+        let mut sol_hit = exceeds(sent, thresholds.sol);
+        let mut rules = Vec::new();
+        if sol_hit {
+            rules.push("sol".to_string());
+        }
+
+        // 关注的钱包按各自的阈值比较，转入转出都算
+        for change in &deltas.changes {
+            let Some(wallet) = thresholds.wallet(&change.account) else { continue };
+            let amount = lamports_to_sol(change.delta.unsigned_abs());
+            if exceeds(amount, wallet.min_sol) {
+                let ratio = amount / wallet.min_sol;
+                sol_hit = true;
+                sol_ratio = sol_ratio.max(ratio);
+                if !rules.iter().any(|r| r == "wallet") {
+                    rules.push("wallet".to_string());
+                }
+            }
+        }
+        let sol = sol_hit.then(|| SolMovement { lamports, transfers: deltas.transfers() });
+
+        // SPL 代币：按 mint 分组，各自和该 mint 的阈值比较
+        let token_transfers = token::token_transfers(tx);
+        let mut mints: Vec<&str> = token_transfers.iter().map(|t| t.mint.as_str()).collect();
+        mints.dedup();
+        let mut tokens = Vec::new();
+        let mut ratio = if sol.is_some() { sol_ratio } else { 0.0 };
+        for mint in mints {
+            let Some(threshold) = thresholds.token(mint) else { continue };
+            let transfers: Vec<TokenTransfer> =
+                token_transfers.iter().filter(|t| t.mint == mint).cloned().collect();
+            let amount: f64 = transfers.iter().map(|t| t.ui_amount()).sum();
+            if !exceeds(amount, threshold.min_amount) { continue; }
+            ratio = ratio.max(amount / threshold.min_amount);
+            rules.push(format!("token:{}", threshold.symbol.as_deref().unwrap_or(mint)));
+
+            tokens.push(TokenMovement {
+                mint: mint.to_string(),
+                symbol: threshold.symbol.clone(),
+                amount,
+                transfers,
+            });
+        }
+
+        if sol.is_none() && tokens.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            signature: tx.signature.clone(),
+            slot: tx.slot,
+            block_time: tx.block_time,
+            fee: deltas.fee,
+            fee_payer: deltas.fee_payer,
+            severity: Severity::from_ratio(ratio),
+            // getTransaction 最低只支持 confirmed
+            status: TxStatus::Confirmed,
+            sol,
+            tokens,
+            memo: memo(tx),
+            feed: String::new(),
+            rules,
+        })
+    }
+
+    // 事件里 SOL 的总流出，没有超过阈值时为 0
+    pub fn sol_amount(&self) -> f64 {
+        self.sol.as_ref().map_or(0.0, SolMovement::amount)
+    }
+
+    // 付款人或任意一笔 SOL / 代币流向涉及该地址
+    pub fn involves(&self, address: &Pubkey) -> bool {
+        self.fee_payer == *address
+            || self.sol.iter().flat_map(|s| &s.transfers).any(|t| t.from == *address || t.to == *address)
+            || self.tokens.iter().flat_map(|m| &m.transfers).any(|t| t.from == *address || t.to == *address)
+    }
+}
+
+// SOL、关注钱包、代币三种阈值统一按"严格超过"判断，刚好等于阈值不报警
+pub fn exceeds(amount: f64, threshold: f64) -> bool {
+    amount > threshold
+}
+
+// Memo 程序的日志格式: Program log: Memo (len 5): "hello"
+fn memo(tx: &FetchedTransaction) -> Option<String> {
+    let OptionSerializer::Some(logs) = &tx.meta.log_messages else { return None };
+    logs.iter().find_map(|line| {
+        let (_, text) = line.strip_prefix("Program log: Memo (len ")?.split_once("): ")?;
+        // 日志里是 Rust 的 {:?} 输出，绝大多数情况下和 JSON 字符串兼容
+        Some(serde_json::from_str::<String>(text).unwrap_or_else(|_| text.trim_matches('"').to_string()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::WalletThreshold;
+    use std::str::FromStr;
+
+    #[test]
+    fn watched_wallet_uses_its_own_threshold() {
+        let json = include_str!("../tests/fixtures/legacy_transfer.json");
+        let tx = FetchedTransaction::parse(serde_json::from_str(json).unwrap()).unwrap();
+        let mut thresholds = ThresholdConfig { sol: 1000.0, ..Default::default() };
+        assert!(WhaleEvent::detect(&tx, &thresholds).is_none());
+
+        // 收款方收到 250 SOL，超过它自己的 10 SOL 阈值 25 倍
+        let receiver = Pubkey::from_str("DTKntNWAAidsxiD8QkMscLbGJxTEayn6ecLaVt635Tzz").unwrap();
+        thresholds.wallets.push(WalletThreshold { address: receiver, min_sol: 10.0 });
+        let event = WhaleEvent::detect(&tx, &thresholds).unwrap();
+        assert_eq!(event.sol_amount(), 250.0);
+        assert_eq!(event.severity, Severity::Warning);
+        assert_eq!(event.rules, vec!["wallet"]);
+    }
+
+    #[test]
+    fn amount_equal_to_threshold_does_not_alert() {
+        let json = include_str!("../tests/fixtures/legacy_transfer.json");
+        let tx = FetchedTransaction::parse(serde_json::from_str(json).unwrap()).unwrap();
+        let receiver = Pubkey::from_str("DTKntNWAAidsxiD8QkMscLbGJxTEayn6ecLaVt635Tzz").unwrap();
+
+        // 这笔交易正好转了 250 SOL
+        let mut thresholds = ThresholdConfig { sol: 250.0, ..Default::default() };
+        assert!(WhaleEvent::detect(&tx, &thresholds).is_none());
+        thresholds.wallets.push(WalletThreshold { address: receiver, min_sol: 250.0 });
+        assert!(WhaleEvent::detect(&tx, &thresholds).is_none());
+        thresholds.wallets[0].min_sol = 249.0;
+        assert_eq!(WhaleEvent::detect(&tx, &thresholds).unwrap().rules, vec!["wallet"]);
+
+        // 代币：正好 10000 USDC
+        let balance = |index: u8, owner: &str, amount: &str| serde_json::json!({
+            "accountIndex": index,
+            "mint": "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+            "owner": owner,
+            "uiTokenAmount": { "amount": amount, "decimals": 6, "uiAmountString": "" },
+        });
+        let mut json: serde_json::Value = serde_json::from_str(json).unwrap();
+        json["meta"]["preTokenBalances"] = serde_json::json!([balance(1, "4Ypn3UXUUstkpPhJ7ZpyxACjThWPNyKPMwcn6FdeJsx2", "10000000000")]);
+        json["meta"]["postTokenBalances"] = serde_json::json!([
+            balance(1, "4Ypn3UXUUstkpPhJ7ZpyxACjThWPNyKPMwcn6FdeJsx2", "0"),
+            balance(2, &receiver.to_string(), "10000000000"),
+        ]);
+        let tx = FetchedTransaction::parse(serde_json::from_value(json).unwrap()).unwrap();
+        let mut thresholds = ThresholdConfig { sol: 1000.0, ..Default::default() };
+        assert!(WhaleEvent::detect(&tx, &thresholds).is_none());
+        thresholds.tokens[0].min_amount = 9999.0;
+        assert_eq!(WhaleEvent::detect(&tx, &thresholds).unwrap().rules, vec!["token:USDC"]);
+    }
+}
@@ -0,0 +1,635 @@
+use crate::backoff::Backoff;
+use crate::config::{Config, FeedConfig, FeedMode, LogsFilter, WalletMethod, WALLET_FEED};
+use crate::dedup::SeenSignatures;
+use crate::event::exceeds;
+use crate::metrics::METRICS;
+use crate::queue::SignatureQueue;
+use crate::watchlist::Watchlist;
+use futures::stream::{self, BoxStream, StreamExt};
+use solana_account_decoder::{UiAccount, UiAccountEncoding};
+use solana_client::nonblocking::pubsub_client::PubsubClient;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcTransactionLogsConfig, RpcTransactionLogsFilter};
+use solana_client::rpc_response::{Response, RpcLogsResponse};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::native_token::lamports_to_sol;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use std::collections::HashMap;
+use std::str::FromStr;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+use tokio::time::Instant;
+use tracing::{Instrument, info, info_span, warn};
+
+// 断线后补数据的上限：getSignaturesForAddress 每页最多 1000 条
+const BACKFILL_PAGE_SIZE: usize = 1000;
+const BACKFILL_MAX_PAGES: usize = 10;
+
+// accountSubscribe 只推送新余额，按通知的 slot 去查签名；processed 的通知可能比签名索引早到，多查几次
+const RESOLVE_PAGE_SIZE: usize = 20;
+const RESOLVE_ATTEMPTS: usize = 5;
+const RESOLVE_DELAY: Duration = Duration::from_millis(800);
+
+// getMultipleAccounts 每次最多 100 个地址
+const ACCOUNTS_PER_REQUEST: usize = 100;
+
+// 最后一次看到的签名和 slot，重连后从这里开始补漏
+#[derive(Clone, Debug, Default)]
+pub struct Cursor {
+    pub signature: Option<String>,
+    pub slot: u64,
+}
+
+// subscribe_once 结束的原因
+enum Ended {
+    StreamClosed,
+    // 关注的钱包变了，需要马上按新列表重新订阅
+    WatchlistChanged,
+    // 连接还在但太久没有推送
+    Stalled,
+}
+
+// 送给处理端的签名，带上是哪一路订阅先发现的
+#[derive(Clone, Debug)]
+pub struct Observed {
+    pub signature: String,
+    pub feed: String,
+}
+
+// 一路订阅
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Target {
+    Logs(FeedConfig),
+    Account(Pubkey),
+}
+
+impl Target {
+    // mention / feeds 这类日志订阅推送不断，可以拿来判断连接是否卡住；
+    // 关注的钱包 (日志或余额订阅) 不活跃时几个小时都没有推送，不能当作卡住
+    fn is_busy(&self) -> bool {
+        matches!(self, Target::Logs(feed) if feed.name != WALLET_FEED)
+    }
+}
+
+// 所有连接上的推送合并成一个流
+enum Notice {
+    Logs(String, Response<RpcLogsResponse>),
+    Account(Pubkey, Response<UiAccount>),
+    // 某个连接断了，整体重连
+    Closed(usize),
+}
+
+#[derive(Debug, Default)]
+pub struct ProviderStats {
+    // 推送过来的签名数，包括其他节点已经送过的
+    pub delivered: AtomicU64,
+    // 比其他节点先送到的签名数
+    pub first: AtomicU64,
+    // 因为太久没有推送被断开重连的次数
+    pub stalls: AtomicU64,
+    // 卡住以后到重新收到推送之前为 true
+    pub stalled: AtomicBool,
+    // 订阅都建好了，断开后变回 false
+    pub connected: AtomicBool,
+    // 关注列表为空，没有要订阅的内容，不算故障
+    pub idle: AtomicBool,
+    // 最后一次收到推送的 unix 时间戳，0 表示还没收到过
+    pub last_notification: AtomicI64,
+}
+
+// /healthz 和 /readyz 里一个 WebSocket 节点的状态
+#[derive(Debug, Serialize)]
+pub struct ProviderStatus {
+    pub provider: String,
+    pub connected: bool,
+    pub idle: bool,
+    pub stalled: bool,
+    pub last_notification_secs_ago: Option<i64>,
+}
+
+// 一个 WebSocket 节点
+pub struct Provider {
+    pub label: String,
+    url: String,
+    pub stats: ProviderStats,
+}
+
+// --- 多个 WebSocket 节点同时订阅，推送合并：同一个签名只有最先送到的那次进入处理队列 ---
+pub struct Providers {
+    pub providers: Vec<Provider>,
+    seen: Mutex<SeenSignatures>,
+}
+
+impl Providers {
+    pub fn new(config: &Config) -> Self {
+        let providers = config
+            .rpc
+            .ws_endpoints()
+            .into_iter()
+            .map(|e| Provider { label: e.label(), url: e.url, stats: ProviderStats::default() })
+            .collect();
+        Self { providers, seen: Mutex::new(SeenSignatures::in_memory(&config.dedup)) }
+    }
+
+    // 队列已经关闭时返回 false
+    async fn deliver(&self, provider: usize, observed: Observed, queue: &SignatureQueue) -> bool {
+        let Provider { label, stats, .. } = &self.providers[provider];
+        stats.delivered.fetch_add(1, Ordering::Relaxed);
+        METRICS.notifications.with_label_values(&[&observed.feed, label]).inc();
+        if !self.seen.lock().unwrap().insert(&observed.signature) {
+            return true;
+        }
+        stats.first.fetch_add(1, Ordering::Relaxed);
+        queue.push(observed).await
+    }
+
+    pub fn status(&self, now: i64) -> Vec<ProviderStatus> {
+        self.providers
+            .iter()
+            .map(|p| {
+                let last = p.stats.last_notification.load(Ordering::Relaxed);
+                ProviderStatus {
+                    provider: p.label.clone(),
+                    connected: p.stats.connected.load(Ordering::Relaxed),
+                    idle: p.stats.idle.load(Ordering::Relaxed),
+                    stalled: p.stats.stalled.load(Ordering::Relaxed),
+                    last_notification_secs_ago: (last > 0).then(|| now - last),
+                }
+            })
+            .collect()
+    }
+
+    // 统计日志里的一段："helius 先到 120/130 | triton 先到 10/128 (卡住)"
+    pub fn summary(&self) -> String {
+        let parts: Vec<String> = self
+            .providers
+            .iter()
+            .map(|p| {
+                let stats = &p.stats;
+                let mut part = format!(
+                    "{} 先到 {}/{}",
+                    p.label,
+                    stats.first.load(Ordering::Relaxed),
+                    stats.delivered.load(Ordering::Relaxed)
+                );
+                if stats.stalled.load(Ordering::Relaxed) {
+                    part.push_str(" (卡住)");
+                }
+                part
+            })
+            .collect();
+        parts.join(" | ")
+    }
+}
+
+// --- 每个 WebSocket 节点各自一个带守护的订阅循环：断线自动重连 + 补漏，一个节点出问题不影响其他节点 ---
+pub async fn run(
+    config: Arc<Config>,
+    rpc: Arc<RpcClient>,
+    watchlist: Arc<Watchlist>,
+    queue: Arc<SignatureQueue>,
+    providers: Arc<Providers>,
+) -> anyhow::Result<()> {
+    // 每个节点一个 span，订阅循环里的日志都带上节点名
+    let loops = (0..providers.providers.len()).map(|i| {
+        let span = info_span!("feed", provider = %providers.providers[i].label);
+        run_provider(&config, &rpc, &watchlist, &queue, &providers, i).instrument(span)
+    });
+    futures::future::join_all(loops).await;
+    Ok(())
+}
+
+async fn run_provider(
+    config: &Config,
+    rpc: &Arc<RpcClient>,
+    watchlist: &Watchlist,
+    queue: &SignatureQueue,
+    providers: &Providers,
+    provider: usize,
+) {
+    let shared = Shared { config, rpc, watchlist, queue, providers };
+    let reconnects = |reason: &str| METRICS.ws_reconnects.with_label_values(&[&providers.providers[provider].label, reason]).inc();
+    let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(60));
+    let mut cursor = Cursor::default();
+    let mut changes = watchlist.subscribe();
+
+    let stats = &providers.providers[provider].stats;
+
+    loop {
+        let ended = subscribe_once(&shared, provider, &mut changes, &mut cursor, &mut backoff).await;
+        stats.connected.store(false, Ordering::Relaxed);
+        stats.idle.store(false, Ordering::Relaxed);
+        match ended {
+            Ok(Ended::WatchlistChanged) => {
+                info!("🔄 关注列表已更新，重新订阅...");
+                reconnects("watchlist");
+                continue;
+            }
+            Ok(Ended::StreamClosed) => {
+                warn!("⚠️ WebSocket 流已结束");
+                reconnects("closed");
+            }
+            Ok(Ended::Stalled) => reconnects("stalled"),
+            Err(e) => {
+                warn!(error = %e, "⚠️ WebSocket 出错");
+                reconnects("error");
+            }
+        }
+
+        // 消费者已经退出，没必要再重连
+        if queue.is_closed() {
+            return;
+        }
+
+        let delay = backoff.next_delay();
+        info!(delay_secs = delay.as_secs(), "🔁 稍后重连...");
+        tokio::time::sleep(delay).await;
+    }
+}
+
+// 所有节点的订阅循环共用的东西
+#[derive(Clone, Copy)]
+struct Shared<'a> {
+    config: &'a Config,
+    rpc: &'a Arc<RpcClient>,
+    watchlist: &'a Watchlist,
+    queue: &'a SignatureQueue,
+    providers: &'a Providers,
+}
+
+async fn subscribe_once(
+    shared: &Shared<'_>,
+    provider: usize,
+    changes: &mut watch::Receiver<()>,
+    cursor: &mut Cursor,
+    backoff: &mut Backoff,
+) -> anyhow::Result<Ended> {
+    let Shared { config, rpc, watchlist, queue, providers } = *shared;
+    let Provider { url, stats, .. } = &providers.providers[provider];
+    changes.mark_unchanged();
+    let wallets = watchlist.wallets();
+    let subscription = &config.subscription;
+    let (groups, dropped) = plan_connections(
+        targets(config, &wallets),
+        subscription.wallets.per_connection,
+        subscription.wallets.max_connections,
+    );
+    if dropped > 0 {
+        warn!(
+            connections = groups.len(),
+            per_connection = subscription.wallets.per_connection,
+            dropped,
+            "⚠️ 订阅数超过上限，部分钱包没有订阅"
+        );
+    }
+    if groups.is_empty() {
+        info!("💤 关注列表为空，等待 /watch 添加钱包...");
+        stats.idle.store(true, Ordering::Relaxed);
+        return match changes.changed().await {
+            Ok(()) => Ok(Ended::WatchlistChanged),
+            Err(_) => Ok(Ended::StreamClosed),
+        };
+    }
+
+    info!(connections = groups.len(), "📡 连接 WebSocket...");
+    let mut clients = Vec::with_capacity(groups.len());
+    for _ in &groups {
+        clients.push(PubsubClient::new(url).await?);
+    }
+    let commitment = Some(subscription.commitment.to_config());
+
+    // mentions 只能填一个地址，每个地址单独订阅一路，同一个连接上的合并，连接断了补一条 Closed
+    let mut connections = Vec::with_capacity(groups.len());
+    for (i, (client, group)) in clients.iter().zip(&groups).enumerate() {
+        let mut streams: Vec<BoxStream<'_, Notice>> = Vec::with_capacity(group.len());
+        for target in group {
+            match target {
+                Target::Logs(feed) => {
+                    let (logs, _unsub) = client.logs_subscribe(logs_filter(feed), RpcTransactionLogsConfig { commitment }).await?;
+                    let name = feed.name.clone();
+                    streams.push(logs.map(move |r| Notice::Logs(name.clone(), r)).boxed());
+                }
+                &Target::Account(address) => {
+                    let account_config = RpcAccountInfoConfig {
+                        encoding: Some(UiAccountEncoding::Base64),
+                        commitment,
+                        ..Default::default()
+                    };
+                    let (account, _unsub) = client.account_subscribe(&address, Some(account_config)).await?;
+                    streams.push(account.map(move |r| Notice::Account(address, r)).boxed());
+                }
+            }
+        }
+        connections.push(stream::select_all(streams).chain(stream::iter([Notice::Closed(i)])));
+    }
+    let mut stream = stream::select_all(connections);
+    backoff.reset();
+    stats.connected.store(true, Ordering::Relaxed);
+
+    // accountSubscribe 只给新余额，先记下当前余额才能算出变化量
+    let mut balances = match subscription.wallets.method {
+        WalletMethod::Account => initial_balances(rpc, &groups).await?,
+        WalletMethod::Logs => HashMap::new(),
+    };
+    // 按余额变化查到的签名送回这里，和日志推送的一起去重
+    let (resolved_tx, mut resolved_rx) = mpsc::unbounded_channel::<(String, u64)>();
+
+    // 先订阅再补漏，这样断线期间的空档两头都能覆盖到；all / allWithVotes 没有地址可查，只能补 mentions
+    if cursor.signature.is_some() {
+        let mut total = 0;
+        for target in groups.iter().flatten() {
+            let (address, feed) = match target {
+                Target::Logs(FeedConfig { filter: LogsFilter::Mentions, mention: Some(mention), name }) => {
+                    (Pubkey::from_str(mention)?, name.as_str())
+                }
+                Target::Logs(_) => continue,
+                Target::Account(address) => (*address, WALLET_FEED),
+            };
+            match backfill(rpc, &address, cursor).await {
+                Ok(missed) => {
+                    total += missed.len();
+                    for signature in missed {
+                        let observed = Observed { signature, feed: feed.to_string() };
+                        if !providers.deliver(provider, observed, queue).await { return Ok(Ended::StreamClosed); }
+                    }
+                }
+                Err(e) => warn!(%address, error = %e, "⚠️ 补漏失败"),
+            }
+        }
+        info!(count = total, "🩹 补漏完成");
+    }
+
+    let feeds = subscription.feeds().len();
+    match subscription.mode {
+        FeedMode::All => info!(feeds, wallets = wallets.len(), "🎧 监听中... (等待巨鲸出现)"),
+        FeedMode::Watchlist => info!(wallets = wallets.len(), "🎧 监听中... (只关注钱包)"),
+    }
+
+    // 连接还在但一直没有推送 (节点卡住、订阅被悄悄丢掉) 时断开重连，其他节点照常推送。
+    // 只看推送频繁的日志订阅，只订阅了关注钱包的节点不检测，钱包的推送也不续期
+    let stall = if groups.iter().flatten().any(Target::is_busy) {
+        Duration::from_secs(subscription.stall_secs)
+    } else {
+        Duration::ZERO
+    };
+    let mut deadline = Instant::now() + stall;
+
+    loop {
+        let (signature, feed, slot) = tokio::select! {
+            notice = stream.next() => {
+                if matches!(&notice, Some(Notice::Logs(feed, _)) if feed != WALLET_FEED) {
+                    deadline = Instant::now() + stall;
+                }
+                stats.last_notification.store(chrono::Utc::now().timestamp(), Ordering::Relaxed);
+                if stats.stalled.swap(false, Ordering::Relaxed) {
+                    info!("✅ WebSocket 恢复推送");
+                }
+                match notice {
+                    Some(Notice::Logs(feed, response)) => {
+                        if response.value.err.is_some() { continue; }
+                        (response.value.signature, feed, response.context.slot)
+                    }
+                    Some(Notice::Account(address, response)) => {
+                        let slot = response.context.slot;
+                        let lamports = response.value.lamports;
+                        let Some(previous) = balances.insert(address, lamports) else { continue };
+                        let Some(wallet) = watchlist.thresholds().wallet(&address).cloned() else { continue };
+                        if !exceeds(lamports_to_sol(lamports.abs_diff(previous)), wallet.min_sol) { continue; }
+
+                        let (rpc, resolved) = (rpc.clone(), resolved_tx.clone());
+                        tokio::spawn(
+                            async move {
+                                if let Err(e) = resolve_signatures(&rpc, &address, slot, &resolved).await {
+                                    warn!(%address, slot, error = %e, "⚠️ 查询钱包的交易失败");
+                                }
+                            }
+                            .in_current_span(),
+                        );
+                        continue;
+                    }
+                    Some(Notice::Closed(i)) => {
+                        warn!(connection = i + 1, "⚠️ WebSocket 连接已断开");
+                        break;
+                    }
+                    None => break,
+                }
+            },
+            Some((signature, slot)) = resolved_rx.recv() => (signature, WALLET_FEED.to_string(), slot),
+            Ok(()) = changes.changed() => return Ok(Ended::WatchlistChanged),
+            _ = tokio::time::sleep_until(deadline), if !stall.is_zero() => {
+                let stalls = stats.stalls.fetch_add(1, Ordering::Relaxed) + 1;
+                stats.stalled.store(true, Ordering::Relaxed);
+                warn!(stall_secs = stall.as_secs(), stalls, "🐢 WebSocket 太久没有推送，判定卡住，断开重连");
+                return Ok(Ended::Stalled);
+            }
+        };
+
+        cursor.signature = Some(signature.clone());
+        cursor.slot = cursor.slot.max(slot);
+
+        if !providers.deliver(provider, Observed { signature, feed }, queue).await { break; }
+    }
+
+    Ok(Ended::StreamClosed)
+}
+
+// 要订阅的内容：watchlist 模式下只订阅关注的钱包
+fn targets(config: &Config, wallets: &[Pubkey]) -> Vec<Target> {
+    let subscription = &config.subscription;
+    let mut targets = Vec::with_capacity(wallets.len() + 1);
+    if subscription.mode == FeedMode::All {
+        targets.extend(subscription.feeds().into_iter().map(Target::Logs));
+    }
+    targets.extend(wallets.iter().map(|&wallet| match subscription.wallets.method {
+        WalletMethod::Logs => Target::Logs(FeedConfig {
+            name: WALLET_FEED.to_string(),
+            filter: LogsFilter::Mentions,
+            mention: Some(wallet.to_string()),
+        }),
+        WalletMethod::Account => Target::Account(wallet),
+    }));
+    targets
+}
+
+fn logs_filter(feed: &FeedConfig) -> RpcTransactionLogsFilter {
+    match feed.filter {
+        LogsFilter::All => RpcTransactionLogsFilter::All,
+        LogsFilter::AllWithVotes => RpcTransactionLogsFilter::AllWithVotes,
+        LogsFilter::Mentions => RpcTransactionLogsFilter::Mentions(feed.mention.iter().cloned().collect()),
+    }
+}
+
+// 按每个连接的订阅上限分组，超出连接数上限的丢掉，返回分组和丢掉的个数
+fn plan_connections(targets: Vec<Target>, per_connection: usize, max_connections: usize) -> (Vec<Vec<Target>>, usize) {
+    let capacity = per_connection * max_connections;
+    let dropped = targets.len().saturating_sub(capacity);
+    let groups = targets
+        .into_iter()
+        .take(capacity)
+        .collect::<Vec<_>>()
+        .chunks(per_connection)
+        .map(<[Target]>::to_vec)
+        .collect();
+    (groups, dropped)
+}
+
+async fn initial_balances(rpc: &RpcClient, groups: &[Vec<Target>]) -> anyhow::Result<HashMap<Pubkey, u64>> {
+    let accounts: Vec<Pubkey> = groups
+        .iter()
+        .flatten()
+        .filter_map(|t| match t {
+            Target::Account(address) => Some(*address),
+            Target::Logs(_) => None,
+        })
+        .collect();
+
+    let mut balances = HashMap::with_capacity(accounts.len());
+    for chunk in accounts.chunks(ACCOUNTS_PER_REQUEST) {
+        let infos = rpc.get_multiple_accounts(chunk).await?;
+        for (address, info) in chunk.iter().zip(infos) {
+            // 还不存在的账户余额按 0 算
+            balances.insert(*address, info.map_or(0, |a| a.lamports));
+        }
+    }
+    Ok(balances)
+}
+
+// 查这个钱包在通知 slot 上的交易，找到后连同 slot 送回订阅循环
+async fn resolve_signatures(
+    rpc: &RpcClient,
+    address: &Pubkey,
+    slot: u64,
+    resolved: &mpsc::UnboundedSender<(String, u64)>,
+) -> anyhow::Result<()> {
+    for _ in 0..RESOLVE_ATTEMPTS {
+        let page = rpc
+            .get_signatures_for_address_with_config(
+                address,
+                GetConfirmedSignaturesForAddress2Config {
+                    limit: Some(RESOLVE_PAGE_SIZE),
+                    commitment: Some(CommitmentConfig::confirmed()),
+                    ..Default::default()
+                },
+            )
+            .await?;
+
+        let found: Vec<String> = page
+            .into_iter()
+            .filter(|s| s.slot == slot && s.err.is_none())
+            .map(|s| s.signature)
+            .collect();
+        if !found.is_empty() {
+            for signature in found.into_iter().rev() {
+                let _ = resolved.send((signature, slot));
+            }
+            return Ok(());
+        }
+        tokio::time::sleep(RESOLVE_DELAY).await;
+    }
+    anyhow::bail!("slot {} 的交易在 {} 次查询后仍未出现", slot, RESOLVE_ATTEMPTS)
+}
+
+// 用 getSignaturesForAddress 从最新往回翻，直到碰到上次看到的签名或更早的 slot，按时间顺序返回
+async fn backfill(rpc: &RpcClient, address: &Pubkey, cursor: &Cursor) -> anyhow::Result<Vec<String>> {
+    let until = match &cursor.signature {
+        Some(sig) => Some(Signature::from_str(sig)?),
+        None => return Ok(Vec::new()),
+    };
+
+    let mut missed = Vec::new();
+    let mut before = None;
+
+    'pages: for _ in 0..BACKFILL_MAX_PAGES {
+        let page = rpc
+            .get_signatures_for_address_with_config(
+                address,
+                GetConfirmedSignaturesForAddress2Config {
+                    before,
+                    until,
+                    limit: Some(BACKFILL_PAGE_SIZE),
+                    // getSignaturesForAddress 不支持 processed
+                    commitment: Some(CommitmentConfig::confirmed()),
+                },
+            )
+            .await?;
+
+        let Some(last) = page.last() else { break };
+        before = Some(Signature::from_str(&last.signature)?);
+        let full_page = page.len() == BACKFILL_PAGE_SIZE;
+
+        for status in page {
+            // processed 阶段看到的签名可能没被确认，until 就永远碰不到，这里用 slot 兜底
+            if status.slot < cursor.slot { break 'pages; }
+            if status.err.is_some() { continue; }
+            missed.push(status.signature);
+        }
+
+        if !full_page { break; }
+    }
+
+    // 接口返回的是从新到旧
+    missed.reverse();
+    Ok(missed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{OverflowPolicy, WalletSubscriptionConfig, WsEndpointConfig};
+
+    #[test]
+    fn spreads_wallets_over_bounded_connections() {
+        let mut config = Config::default();
+        config.subscription.mode = FeedMode::Watchlist;
+        config.subscription.wallets = WalletSubscriptionConfig { method: WalletMethod::Account, per_connection: 100, max_connections: 2 };
+        let wallets: Vec<Pubkey> = (0..250).map(|_| Pubkey::new_unique()).collect();
+
+        let all = targets(&config, &wallets);
+        assert_eq!(all[0], Target::Account(wallets[0]));
+        let (groups, dropped) = plan_connections(all, 100, 2);
+        assert_eq!(groups.iter().map(Vec::len).collect::<Vec<_>>(), vec![100, 100]);
+        assert_eq!(dropped, 50);
+        // 只有关注钱包的连接不做卡住检测
+        assert!(!groups.iter().flatten().any(Target::is_busy));
+
+        // all 模式下 mention 占第一个连接的第一路
+        config.subscription.mode = FeedMode::All;
+        config.subscription.wallets.method = WalletMethod::Logs;
+        let (groups, dropped) = plan_connections(targets(&config, &wallets[..150]), 100, 2);
+        assert_eq!(groups[0][0], Target::Logs(config.subscription.feeds()[0].clone()));
+        assert!(groups[0][0].is_busy());
+        assert!(!groups[0][1].is_busy());
+        assert_eq!(groups.iter().map(Vec::len).collect::<Vec<_>>(), vec![100, 51]);
+        assert_eq!(dropped, 0);
+    }
+
+    #[tokio::test]
+    async fn merges_providers_and_counts_first_delivery() {
+        let mut config = Config::default();
+        config.rpc.ws_endpoints = vec![
+            WsEndpointConfig { name: Some("helius".to_string()), url: "wss://a".to_string() },
+            WsEndpointConfig { name: None, url: "wss://b.example.com".to_string() },
+        ];
+        let providers = Providers::new(&config);
+        let queue = SignatureQueue::new(10, OverflowPolicy::Block);
+        let observed = |signature: &str| Observed { signature: signature.to_string(), feed: "mention".to_string() };
+
+        // 两个节点都送来 a，第二个节点先到；b 只有 helius 送来
+        assert!(providers.deliver(1, observed("a"), &queue).await);
+        assert!(providers.deliver(0, observed("a"), &queue).await);
+        assert!(providers.deliver(0, observed("b"), &queue).await);
+        assert_eq!(queue.depth(), 2);
+
+        let [helius, other] = &providers.providers[..] else { panic!() };
+        assert_eq!(other.label, "b.example.com");
+        assert_eq!(helius.stats.delivered.load(Ordering::Relaxed), 2);
+        assert_eq!(helius.stats.first.load(Ordering::Relaxed), 1);
+        assert_eq!(other.stats.first.load(Ordering::Relaxed), 1);
+        assert_eq!(providers.summary(), "helius 先到 1/2 | b.example.com 先到 1/1");
+    }
+}
@@ -0,0 +1,236 @@
+use anyhow::{anyhow, bail};
+use serde_json::json;
+use solana_client::client_error::ClientError;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_client::rpc_request::RpcRequest;
+use solana_sdk::address_lookup_table::state::AddressLookupTable;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::message::v0::MessageAddressTableLookup;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_transaction_status::option_serializer::OptionSerializer;
+use solana_transaction_status::{
+    EncodedConfirmedTransactionWithStatusMeta, UiTransactionEncoding,
+    UiTransactionStatusMeta,
+};
+use std::str::FromStr;
+
+// 拉下来并解析好的交易：account_keys 顺序和 pre/post_balances 一一对应
+// (静态账户 + 查找表里的 writable + 查找表里的 readonly)
+#[derive(Debug)]
+pub struct FetchedTransaction {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub account_keys: Vec<Pubkey>,
+    pub meta: UiTransactionStatusMeta,
+    // meta 里没带 loadedAddresses 时，需要自己去链上解析的查找表
+    pub unresolved_lookups: Vec<MessageAddressTableLookup>,
+}
+
+// 节点还没有这笔交易时返回 None (processed 阶段推送的签名经常这样)，调用方稍后重试
+pub async fn fetch_transaction(
+    client: &RpcClient,
+    signature: &Signature,
+    commitment: CommitmentConfig,
+) -> anyhow::Result<Option<FetchedTransaction>> {
+    let config = RpcTransactionConfig {
+        // base64 才能拿到原始 message，查找表信息也在里面
+        encoding: Some(UiTransactionEncoding::Base64),
+        commitment: Some(commitment),
+        // 不带这个参数，所有 v0 交易都会直接报错
+        max_supported_transaction_version: Some(0),
+    };
+    // get_transaction_with_config 把 null 当成反序列化错误，分不清是没找到还是出错了，这里直接按 Option 解析
+    let encoded: Option<EncodedConfirmedTransactionWithStatusMeta> =
+        client.send(RpcRequest::GetTransaction, json!([signature.to_string(), config])).await?;
+    let Some(encoded) = encoded else { return Ok(None) };
+
+    let mut fetched = FetchedTransaction::parse(encoded)?;
+    if !fetched.unresolved_lookups.is_empty() {
+        resolve_lookup_tables(client, &mut fetched).await?;
+    }
+    fetched.check_balances()?;
+
+    Ok(Some(fetched))
+}
+
+// RPC 调用本身出错 (网络、限流、节点异常)，和交易数据解析失败区分开，前者值得重试
+pub fn is_rpc_error(e: &anyhow::Error) -> bool {
+    e.downcast_ref::<ClientError>().is_some()
+}
+
+impl FetchedTransaction {
+    pub fn parse(encoded: EncodedConfirmedTransactionWithStatusMeta) -> anyhow::Result<Self> {
+        let versioned = encoded
+            .transaction
+            .transaction
+            .decode()
+            .ok_or_else(|| anyhow!("交易解码失败"))?;
+        let meta = encoded.transaction.meta.ok_or_else(|| anyhow!("交易缺少 meta"))?;
+        let signature = versioned
+            .signatures
+            .first()
+            .ok_or_else(|| anyhow!("交易没有签名"))?
+            .to_string();
+
+        let mut account_keys = versioned.message.static_account_keys().to_vec();
+        let lookups = versioned.message.address_table_lookups().unwrap_or_default().to_vec();
+
+        let unresolved_lookups = match meta.loaded_addresses.as_ref() {
+            OptionSerializer::Some(loaded) => {
+                for address in loaded.writable.iter().chain(loaded.readonly.iter()) {
+                    account_keys.push(Pubkey::from_str(address)?);
+                }
+                Vec::new()
+            }
+            // 老节点不返回 loadedAddresses，只能自己查表
+            _ => lookups,
+        };
+
+        Ok(Self {
+            signature,
+            slot: encoded.slot,
+            block_time: encoded.block_time,
+            account_keys,
+            meta,
+            unresolved_lookups,
+        })
+    }
+
+    fn check_balances(&self) -> anyhow::Result<()> {
+        let n = self.account_keys.len();
+        if self.meta.pre_balances.len() != n || self.meta.post_balances.len() != n {
+            bail!(
+                "账户数量和余额数量对不上: {} 个账户, pre {} / post {}",
+                n,
+                self.meta.pre_balances.len(),
+                self.meta.post_balances.len()
+            );
+        }
+        Ok(())
+    }
+}
+
+// 从链上读取查找表，按 writable 全部在前、readonly 在后的顺序追加到 account_keys
+async fn resolve_lookup_tables(client: &RpcClient, fetched: &mut FetchedTransaction) -> anyhow::Result<()> {
+    let table_keys: Vec<Pubkey> = fetched.unresolved_lookups.iter().map(|l| l.account_key).collect();
+    let accounts = client.get_multiple_accounts(&table_keys).await?;
+
+    let mut tables = Vec::with_capacity(accounts.len());
+    for (key, account) in table_keys.iter().zip(accounts) {
+        let account = account.ok_or_else(|| anyhow!("查找表 {} 不存在", key))?;
+        let table = AddressLookupTable::deserialize(&account.data)
+            .map_err(|e| anyhow!("查找表 {} 解析失败: {}", key, e))?;
+        tables.push(table.addresses.into_owned());
+    }
+
+    let mut writable = Vec::new();
+    let mut readonly = Vec::new();
+    for (lookup, addresses) in fetched.unresolved_lookups.iter().zip(&tables) {
+        for (indexes, out) in [(&lookup.writable_indexes, &mut writable), (&lookup.readonly_indexes, &mut readonly)] {
+            for &i in indexes {
+                let address = addresses
+                    .get(i as usize)
+                    .ok_or_else(|| anyhow!("查找表 {} 下标 {} 越界", lookup.account_key, i))?;
+                out.push(*address);
+            }
+        }
+    }
+
+    fetched.account_keys.extend(writable);
+    fetched.account_keys.extend(readonly);
+    fetched.unresolved_lookups.clear();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sinks::mock_server::MockServer;
+
+    const LEGACY: &str = include_str!("../tests/fixtures/legacy_transfer.json");
+    const V0: &str = include_str!("../tests/fixtures/v0_lookup_transfer.json");
+
+    fn load(json: &str) -> EncodedConfirmedTransactionWithStatusMeta {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn key(s: &str) -> Pubkey {
+        Pubkey::from_str(s).unwrap()
+    }
+
+    #[tokio::test]
+    async fn missing_transaction_is_not_an_error() {
+        let server = MockServer::start(vec![
+            (200, r#"{"jsonrpc":"2.0","result":null,"id":1}"#),
+            (503, "Service Unavailable"),
+        ])
+        .await;
+        let client = RpcClient::new(server.url.clone());
+        let signature = Signature::default();
+
+        let fetched = fetch_transaction(&client, &signature, CommitmentConfig::confirmed()).await.unwrap();
+        assert!(fetched.is_none());
+        let err = fetch_transaction(&client, &signature, CommitmentConfig::confirmed()).await.unwrap_err();
+        assert!(is_rpc_error(&err), "{:#}", err);
+        assert_eq!(server.requests()[0].json()["method"], "getTransaction");
+    }
+
+    #[test]
+    fn parses_legacy_transaction() {
+        let tx = FetchedTransaction::parse(load(LEGACY)).unwrap();
+
+        assert_eq!(tx.slot, 287654321);
+        assert_eq!(tx.block_time, Some(1727000000));
+        assert_eq!(
+            tx.signature,
+            "476Quh27MKjtmgciYgbvDcYNPkXR9TEAWP2GVkLQEEHQvLR2bMXbA7xAyY8hs2c4mKHTM4m1X2NRDdJGRxKzGJ8i"
+        );
+        assert_eq!(
+            tx.account_keys,
+            vec![
+                key("4Ypn3UXUUstkpPhJ7ZpyxACjThWPNyKPMwcn6FdeJsx2"),
+                key("DTKntNWAAidsxiD8QkMscLbGJxTEayn6ecLaVt635Tzz"),
+                key("11111111111111111111111111111111"),
+            ]
+        );
+        assert!(tx.unresolved_lookups.is_empty());
+        tx.check_balances().unwrap();
+    }
+
+    #[test]
+    fn parses_v0_transaction_with_loaded_addresses() {
+        let tx = FetchedTransaction::parse(load(V0)).unwrap();
+
+        assert_eq!(tx.slot, 287654400);
+        // 收款方只出现在查找表里，必须排在静态账户后面
+        assert_eq!(
+            tx.account_keys,
+            vec![
+                key("C7U8pSFb8xu8fuT3kQCy37uyHSEQzYTUkJ1Ko3R61HZS"),
+                key("11111111111111111111111111111111"),
+                key("5maYy2W1MwSBNXeJseBzWYAF67wxGEXS3gcURwKq9Ffp"),
+            ]
+        );
+        assert!(tx.unresolved_lookups.is_empty());
+        tx.check_balances().unwrap();
+    }
+
+    #[test]
+    fn v0_without_loaded_addresses_needs_lookup() {
+        let mut json: serde_json::Value = serde_json::from_str(V0).unwrap();
+        json["meta"].as_object_mut().unwrap().remove("loadedAddresses");
+        let tx = FetchedTransaction::parse(serde_json::from_value(json).unwrap()).unwrap();
+
+        assert_eq!(tx.account_keys.len(), 2);
+        assert_eq!(tx.unresolved_lookups.len(), 1);
+        assert_eq!(
+            tx.unresolved_lookups[0].account_key,
+            key("6JQ5jPow35dG5dSwEUHBjBdpMSz7bN8cMBvaN7Qc7p3b")
+        );
+        assert_eq!(tx.unresolved_lookups[0].writable_indexes, vec![1]);
+        assert!(tx.check_balances().is_err());
+    }
+}
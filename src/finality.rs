@@ -0,0 +1,70 @@
+use crate::config::FinalityConfig;
+use crate::event::TxStatus;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::signature::Signature;
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::warn;
+
+// 轮询签名状态直到 finalized；超时还没等到就认为交易所在的分叉被放弃了
+pub async fn wait_for_finality(rpc: &RpcClient, signature: &Signature, config: &FinalityConfig) -> TxStatus {
+    let interval = Duration::from_millis(config.poll_interval_ms);
+    let deadline = Instant::now() + Duration::from_secs(config.timeout_secs);
+
+    while Instant::now() < deadline {
+        tokio::time::sleep(interval).await;
+        match rpc.get_signature_statuses(&[*signature]).await {
+            Ok(response) => {
+                // None 表示节点现在查不到这笔交易，可能还在别的分叉上，继续等
+                if let Some(Some(status)) = response.value.first()
+                    && status.satisfies_commitment(CommitmentConfig::finalized())
+                {
+                    return TxStatus::Finalized;
+                }
+            }
+            Err(e) => warn!(%signature, error = %e, "⚠️ 查询交易状态失败"),
+        }
+    }
+
+    TxStatus::Dropped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sinks::mock_server::MockServer;
+
+    // finalized 的 confirmations 是 null
+    fn status(confirmations: &str, confirmation: &str) -> String {
+        format!(
+            r#"{{"jsonrpc":"2.0","result":{{"context":{{"slot":2}},"value":[{{"slot":1,"confirmations":{},"err":null,"status":{{"Ok":null}},"confirmationStatus":"{}"}}]}},"id":1}}"#,
+            confirmations, confirmation
+        )
+    }
+
+    #[tokio::test]
+    async fn polls_until_finalized() {
+        let (confirmed, finalized) = (status("5", "confirmed"), status("null", "finalized"));
+        let server = MockServer::start(vec![(200, &confirmed), (200, &finalized)]).await;
+        let config = FinalityConfig { poll_interval_ms: 10, timeout_secs: 5 };
+
+        let status = wait_for_finality(&RpcClient::new(server.url.clone()), &Signature::default(), &config).await;
+        assert_eq!(status, TxStatus::Finalized);
+        assert_eq!(server.requests().len(), 2);
+        assert_eq!(server.requests()[0].json()["method"], "getSignatureStatuses");
+    }
+
+    #[tokio::test]
+    async fn times_out_as_dropped() {
+        // 先是节点查不到，之后节点连不上，都要继续等到超时
+        let unknown = r#"{"jsonrpc":"2.0","result":{"context":{"slot":2},"value":[null]},"id":1}"#;
+        let server = MockServer::start(vec![(200, unknown)]).await;
+        let config = FinalityConfig { poll_interval_ms: 10, timeout_secs: 1 };
+
+        let started = Instant::now();
+        let status = wait_for_finality(&RpcClient::new(server.url.clone()), &Signature::default(), &config).await;
+        assert_eq!(status, TxStatus::Dropped);
+        assert!(started.elapsed() >= Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,152 @@
+use crate::config::{Config, FeedMode};
+use crate::feed::{ProviderStatus, Providers};
+use crate::rpc_pool::{EndpointStatus, RpcPool};
+use crate::sinks::{Dispatcher, SinkStatus};
+use serde::Serialize;
+use std::sync::Arc;
+
+// /healthz 和 /readyz 的返回内容，两者格式一样，只是判断失败的条件不同
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub ok: bool,
+    // 失败的原因，ok 时为空
+    pub problems: Vec<String>,
+    pub uptime_secs: i64,
+    pub websocket: Vec<ProviderStatus>,
+    pub rpc: Vec<EndpointStatus>,
+    // 只是报告，报警通道失败不影响 healthz / readyz 的结果
+    pub sinks: Vec<SinkStatus>,
+}
+
+// --- 健康检查：给 Kubernetes 之类的编排系统判断要不要重启 / 是否就绪 ---
+pub struct Health {
+    providers: Arc<Providers>,
+    rpc: RpcPool,
+    dispatcher: Arc<Dispatcher>,
+    max_silence_secs: i64,
+    // watchlist 模式只订阅关注的钱包，钱包不活跃时几个小时没有推送也正常，不按静默判断存活
+    watches_feeds: bool,
+    started: i64,
+}
+
+impl Health {
+    pub fn new(config: &Config, providers: Arc<Providers>, rpc: RpcPool, dispatcher: Arc<Dispatcher>) -> Self {
+        Self {
+            providers,
+            rpc,
+            dispatcher,
+            max_silence_secs: config.http.max_silence_secs as i64,
+            watches_feeds: config.subscription.mode == FeedMode::All,
+            started: chrono::Utc::now().timestamp(),
+        }
+    }
+
+    // 存活：进程还在正常收推送。所有在订阅的节点都太久没有推送，说明卡死了，重启比干等好
+    pub fn liveness(&self) -> Report {
+        self.liveness_at(chrono::Utc::now().timestamp())
+    }
+
+    // 就绪：至少有一个 WebSocket 节点连着 (或者关注列表为空、本来就不用订阅)，并且至少有一个 RPC 节点可用
+    pub fn readiness(&self) -> Report {
+        self.readiness_at(chrono::Utc::now().timestamp())
+    }
+
+    fn liveness_at(&self, now: i64) -> Report {
+        let mut report = self.report(now);
+        if let Some(problem) = self.silence(&report) {
+            report.problems.push(problem);
+        }
+        report.ok = report.problems.is_empty();
+        report
+    }
+
+    fn readiness_at(&self, now: i64) -> Report {
+        let mut report = self.liveness_at(now);
+        if !report.websocket.iter().any(|p| p.connected || p.idle) {
+            report.problems.push("没有已连接的 WebSocket 节点".to_string());
+        }
+        if !report.rpc.iter().any(|e| e.available) {
+            report.problems.push("所有 RPC 节点都在熔断中".to_string());
+        }
+        report.ok = report.problems.is_empty();
+        report
+    }
+
+    // 还没收到过推送的节点从启动时算起；idle 的节点没有订阅，不参与判断
+    fn silence(&self, report: &Report) -> Option<String> {
+        if self.max_silence_secs == 0 || !self.watches_feeds {
+            return None;
+        }
+        let quietest = report
+            .websocket
+            .iter()
+            .filter(|p| !p.idle)
+            .map(|p| p.last_notification_secs_ago.unwrap_or(report.uptime_secs))
+            .min()?;
+        (quietest > self.max_silence_secs)
+            .then(|| format!("所有 WebSocket 节点已经 {} 秒没有推送 (上限 {} 秒)", quietest, self.max_silence_secs))
+    }
+
+    fn report(&self, now: i64) -> Report {
+        Report {
+            ok: true,
+            problems: Vec::new(),
+            uptime_secs: now - self.started,
+            websocket: self.providers.status(now),
+            rpc: self.rpc.status(),
+            sinks: self.dispatcher.status(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::Ordering;
+
+    #[test]
+    fn silent_feeds_fail_liveness() {
+        let config = Config::default();
+        let providers = Arc::new(Providers::new(&config));
+        let dispatcher = Arc::new(Dispatcher::from_config(&config).unwrap());
+        let health = Health::new(&config, providers.clone(), RpcPool::new(&config.rpc), dispatcher);
+        let stats = &providers.providers[0].stats;
+        stats.connected.store(true, Ordering::Relaxed);
+
+        // 启动 10 分钟内一直没有推送还算正常，超过就判定卡死
+        assert!(health.liveness_at(health.started + 600).ok);
+        let report = health.readiness_at(health.started + 601);
+        assert!(!report.ok);
+        assert!(report.problems[0].contains("601"), "{:?}", report.problems);
+
+        stats.last_notification.store(health.started + 590, Ordering::Relaxed);
+        let report = health.readiness_at(health.started + 601);
+        assert!(report.ok, "{:?}", report.problems);
+        assert_eq!(report.websocket[0].last_notification_secs_ago, Some(11));
+
+        // 关注列表为空时本来就没有推送
+        stats.connected.store(false, Ordering::Relaxed);
+        stats.idle.store(true, Ordering::Relaxed);
+        assert!(health.readiness_at(health.started + 10_000).ok);
+    }
+
+    #[test]
+    fn quiet_wallets_pass_liveness() {
+        let mut config = Config::default();
+        config.subscription.mode = FeedMode::Watchlist;
+        let providers = Arc::new(Providers::new(&config));
+        let dispatcher = Arc::new(Dispatcher::from_config(&config).unwrap());
+        let health = Health::new(&config, providers.clone(), RpcPool::new(&config.rpc), dispatcher);
+        let stats = &providers.providers[0].stats;
+
+        // 只订阅了关注钱包，钱包一整天不动也不算卡死
+        stats.connected.store(true, Ordering::Relaxed);
+        let report = health.readiness_at(health.started + 86_400);
+        assert!(report.ok, "{:?}", report.problems);
+
+        // 就绪仍然看连接状态
+        stats.connected.store(false, Ordering::Relaxed);
+        assert!(health.liveness_at(health.started + 86_400).ok);
+        assert!(!health.readiness_at(health.started + 86_400).ok);
+    }
+}
@@ -0,0 +1,88 @@
+use crate::health::{Health, Report};
+use crate::metrics::METRICS;
+use anyhow::Context;
+use hyper::header::CONTENT_TYPE;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use prometheus::{Encoder, TextEncoder};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tracing::{error, info};
+
+// --- 运维 HTTP 接口：/metrics /healthz /readyz ---
+// 绑定失败直接返回错误 (端口被占用时启动就退出)，返回实际监听的地址
+pub fn spawn(addr: SocketAddr, health: Arc<Health>) -> anyhow::Result<SocketAddr> {
+    let make = make_service_fn(move |_| {
+        let health = health.clone();
+        async move { Ok::<_, Infallible>(service_fn(move |req| handle(req, health.clone()))) }
+    });
+    let server = Server::try_bind(&addr).with_context(|| format!("无法监听 {}", addr))?.serve(make);
+    let local = server.local_addr();
+    info!(addr = %local, "📈 HTTP 接口已启动 (/metrics /healthz /readyz)");
+    tokio::spawn(async move {
+        if let Err(e) = server.await {
+            error!(error = %e, "❌ HTTP 接口退出");
+        }
+    });
+    Ok(local)
+}
+
+async fn handle(req: Request<Body>, health: Arc<Health>) -> Result<Response<Body>, Infallible> {
+    let response = match (req.method(), req.uri().path()) {
+        (&Method::GET, "/metrics") => Response::builder()
+            .header(CONTENT_TYPE, TextEncoder::new().format_type())
+            .body(Body::from(METRICS.render())),
+        (&Method::GET, "/healthz") => json(&health.liveness()),
+        (&Method::GET, "/readyz") => json(&health.readiness()),
+        _ => Response::builder().status(StatusCode::NOT_FOUND).body(Body::from("not found\n")),
+    };
+    Ok(response.expect("响应头都是合法的"))
+}
+
+// 检查失败时返回 503，编排系统只看状态码，body 给人排查用
+fn json(report: &Report) -> hyper::http::Result<Response<Body>> {
+    let status = if report.ok { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    let body = serde_json::to_vec_pretty(report).expect("Report 一定能序列化");
+    Response::builder().status(status).header(CONTENT_TYPE, "application/json").body(Body::from(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::config::Config;
+    use crate::feed::Providers;
+    use crate::rpc_pool::RpcPool;
+    use crate::sinks::Dispatcher;
+
+    #[tokio::test]
+    async fn serves_metrics_and_health() {
+        let config = Config::default();
+        let providers = Arc::new(Providers::new(&config));
+        let dispatcher = Arc::new(Dispatcher::from_config(&config).unwrap());
+        let health = Arc::new(Health::new(&config, providers.clone(), RpcPool::new(&config.rpc), dispatcher));
+        let addr = spawn(SocketAddr::from(([127, 0, 0, 1], 0)), health).unwrap();
+        METRICS.queue_dropped.inc();
+
+        let res = reqwest::get(format!("http://{}/metrics", addr)).await.unwrap();
+        assert_eq!(res.status(), 200);
+        assert!(res.headers()[CONTENT_TYPE.as_str()].to_str().unwrap().starts_with("text/plain"));
+        assert!(res.text().await.unwrap().contains("whale_queue_dropped_total"));
+
+        let res = reqwest::get(format!("http://{}/", addr)).await.unwrap();
+        assert_eq!(res.status(), 404);
+
+        // 刚启动还没连上 WebSocket：活着，但没有就绪
+        let res = reqwest::get(format!("http://{}/healthz", addr)).await.unwrap();
+        assert_eq!(res.status(), 200);
+        let res = reqwest::get(format!("http://{}/readyz", addr)).await.unwrap();
+        assert_eq!(res.status(), 503);
+        let body: serde_json::Value = res.json().await.unwrap();
+        assert_eq!(body["problems"][0], "没有已连接的 WebSocket 节点");
+
+        providers.providers[0].stats.connected.store(true, std::sync::atomic::Ordering::Relaxed);
+        let res = reqwest::get(format!("http://{}/readyz", addr)).await.unwrap();
+        assert_eq!(res.status(), 200);
+    }
+}
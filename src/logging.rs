@@ -0,0 +1,14 @@
+use crate::config::{LogConfig, LogFormat};
+use tracing_subscriber::EnvFilter;
+
+// 全局日志：level 已经在 Config::validate 里检查过
+pub fn init(config: &LogConfig) -> anyhow::Result<()> {
+    let filter = EnvFilter::try_new(&config.level)?;
+    let builder = tracing_subscriber::fmt().with_env_filter(filter).with_target(false);
+    let result = match config.format {
+        LogFormat::Text => builder.try_init(),
+        // current_span 带上签名 / 节点这些 span 字段，span_list 太啰嗦不要
+        LogFormat::Json => builder.json().with_current_span(true).with_span_list(false).try_init(),
+    };
+    result.map_err(|e| anyhow::anyhow!("初始化日志失败: {}", e))
+}
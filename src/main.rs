@@ -1,144 +1,269 @@
+mod backoff;
+mod balance;
+mod bot;
+mod config;
+mod dedup;
+mod event;
+mod feed;
+mod fetch;
+mod finality;
+mod health;
+mod http;
+mod logging;
+mod message;
+mod metrics;
+mod queue;
+mod retry;
+mod rpc_pool;
+mod sinks;
+mod token;
+mod watchlist;
+
+use config::Config;
+use dedup::SeenSignatures;
+use event::{TxStatus, WhaleEvent};
+use metrics::METRICS;
+use queue::SignatureQueue;
+use retry::{Pending, Reason, RetryQueue};
+use rpc_pool::RpcPool;
+use sinks::Dispatcher;
+use watchlist::Watchlist;
+use anyhow::Context;
 use dotenv::dotenv;
-use solana_client::nonblocking::pubsub_client::PubsubClient;
-use solana_client::rpc_config::{RpcTransactionLogsConfig, RpcTransactionLogsFilter};
 use solana_client::nonblocking::rpc_client::RpcClient;
-use tokio::sync::mpsc;
-use solana_sdk::commitment_config::CommitmentConfig;
-use futures::StreamExt;
-use solana_transaction_status::UiTransactionEncoding;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 use solana_sdk::signature::Signature;
-use std::env;
+use tracing::{Instrument, error, info, info_span, warn};
 use std::str::FromStr;
 use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+// 每隔多久打印一次队列 / 处理情况
+const STATS_INTERVAL: Duration = Duration::from_secs(60);
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     dotenv().ok();
-    println!("🚀 启动 Solana 巨鲸监控者 (最终完整版)...");
-
-    let ws_url = env::var("WS_URL").expect("WS_URL 未设置");
-    let rpc_url = env::var("RPC_URL").expect("RPC_URL 未设置");
-    
-    // 检查 TG 配置，如果没有配置只会打印警告，不会崩溃
-    if env::var("TELEGRAM_TOKEN").is_err() {
-        println!("⚠️ 未检测到 TELEGRAM_TOKEN，报警功能将不可用");
+
+    // 配置有问题直接报错退出，不要跑到一半才 panic
+    let config = Arc::new(Config::load()?);
+    logging::init(&config.log)?;
+    info!("🚀 启动 Solana 巨鲸监控者 (最终完整版)...");
+
+    // 子命令：重放 webhook 死信后退出
+    if std::env::args().nth(1).as_deref() == Some("replay-dead-letters") {
+        return sinks::webhook::replay_all(&config).await;
+    }
+
+    // 检查报警通道，如果没有配置只会打印警告，不会崩溃
+    let dispatcher = Arc::new(Dispatcher::from_config(&config)?);
+    if dispatcher.is_empty() {
+        warn!("⚠️ 未配置任何报警通道 (TELEGRAM_TOKEN / [[sinks]])，报警功能将不可用");
+    }
+
+    // 关注列表 / 阈值 / 静音状态可以在运行中通过 bot 命令修改
+    let watchlist = Arc::new(Watchlist::load(&config)?);
+    if config.telegram.bot.enabled
+        && let Some(outbox) = dispatcher.telegram()
+    {
+        let bot = bot::Bot::new(&config, outbox.clone(), watchlist.clone(), dispatcher.len())?;
+        tokio::spawn(bot.run());
+    }
+
+    let queue = Arc::new(SignatureQueue::new(config.concurrency.channel_size, config.concurrency.overflow));
+    // 同时处理的签名数有上限，处理不过来时队列按 overflow 策略处理
+    let workers = Arc::new(Semaphore::new(config.concurrency.workers));
+    // 所有 HTTP RPC 请求都经过节点池：按权重分流、限速、熔断、自动切换
+    let rpc_pool = RpcPool::new(&config.rpc);
+    if config.rpc.pool.health_check_interval_secs > 0 {
+        rpc_pool.spawn_health_checks(Duration::from_secs(config.rpc.pool.health_check_interval_secs));
     }
+    let rpc_client = Arc::new(rpc_pool.client());
 
-    let (tx, mut rx) = mpsc::channel::<String>(100);
+    // 多个 WebSocket 节点的推送先在订阅端合并去重，统计每个节点先到的次数
+    let providers = Arc::new(feed::Providers::new(&config));
+
+    // /metrics /healthz /readyz
+    if config.http.enabled {
+        let health = health::Health::new(&config, providers.clone(), rpc_pool.clone(), dispatcher.clone());
+        http::spawn(config.http.listen, Arc::new(health))?;
+    }
+
+    // 同一笔交易可能被多路订阅、重连补漏送来好几次，只处理第一次
+    let mut seen = SeenSignatures::load(&config.dedup)?;
+    if config.dedup.path.is_some() {
+        info!(count = seen.len(), "🧹 读取去重记录");
+    }
+
+    // 还拉不到的交易过一会儿再交给处理端
+    let (retry_queue, mut retry_rx) = RetryQueue::new(&config.retry);
+    let retry_queue = Arc::new(retry_queue);
 
     // --- 后台消费者 ---
-    tokio::spawn(async move {
-        println!("👨‍🔧 后台调度中心已就位...");
-        let rpc_client = RpcClient::new(rpc_url);
-        let client_arc = Arc::new(rpc_client);
+    let client_arc = rpc_client.clone();
+    let consumer_config = config.clone();
+    let consumer_watchlist = watchlist.clone();
+    let consumer_queue = queue.clone();
+    let consumer_providers = providers.clone();
+    let consumer = tokio::spawn(async move {
+        info!(workers = consumer_config.concurrency.workers, "👨‍🔧 后台调度中心已就位...");
+        let mut save_timer = tokio::time::interval(Duration::from_secs(consumer_config.dedup.save_interval_secs));
+        let mut stats_timer = tokio::time::interval(STATS_INTERVAL);
+
+        loop {
+            let pending = tokio::select! {
+                observed = consumer_queue.pop() => match observed {
+                    Some(observed) if seen.insert(&observed.signature) => retry::Pending::new(observed),
+                    Some(_) => continue,
+                    None => break,
+                },
+                // 重试的签名已经在去重记录里了，不再过一遍
+                Some(pending) = retry_rx.recv() => pending,
+                _ = save_timer.tick() => {
+                    if let Err(e) = seen.save() {
+                        warn!(error = format!("{:#}", e), "⚠️ 保存去重记录失败");
+                    }
+                    continue;
+                }
+                _ = stats_timer.tick() => {
+                    let in_flight = consumer_config.concurrency.workers - workers.available_permits();
+                    info!(
+                        queue_depth = consumer_queue.depth(),
+                        queue_capacity = consumer_queue.capacity(),
+                        in_flight,
+                        workers = consumer_config.concurrency.workers,
+                        enqueued = consumer_queue.stats.enqueued.load(Ordering::Relaxed),
+                        dropped = consumer_queue.stats.dropped.load(Ordering::Relaxed),
+                        recovered = retry_queue.stats.recovered.load(Ordering::Relaxed),
+                        abandoned = retry_queue.stats.abandoned.load(Ordering::Relaxed),
+                        providers = consumer_providers.summary(),
+                        "📊 运行统计"
+                    );
+                    continue;
+                }
+            };
+            // 没有空闲的 worker 就在这里等，期间队列继续积压
+            let permit = workers.clone().acquire_owned().await.expect("信号量不会被关闭");
 
-        while let Some(signature) = rx.recv().await {
             let client_ref = client_arc.clone();
-            tokio::spawn(async move {
-                // 处理交易，并不再关心返回值，只负责跑
-                if let Err(_e) = process_transaction(client_ref, signature).await {
-                    // 生产环境下这里可以用 log crate 记录到文件
-                    // eprintln!("❌ Error: {}", e);
+            let config_ref = consumer_config.clone();
+            let dispatcher_ref = dispatcher.clone();
+            let watchlist_ref = consumer_watchlist.clone();
+            let retry_ref = retry_queue.clone();
+            // 这笔交易的所有日志 (拉取、重试、报警、最终确认) 都带上签名
+            let span = info_span!(
+                "tx",
+                signature = %pending.observed.signature,
+                feed = %pending.observed.feed,
+                attempt = pending.attempt,
+            );
+            tokio::spawn(
+                async move {
+                    if let Err(e) = process_transaction(client_ref, config_ref, dispatcher_ref, watchlist_ref, retry_ref, pending, permit).await {
+                        error!(error = format!("{:#}", e), "❌ 处理交易失败");
+                        METRICS.processed.with_label_values(&["error"]).inc();
+                    }
                 }
-            });
+                .instrument(span),
+            );
+        }
+
+        // 退出前把还没到保存时间的去重记录也写下来，重启后补漏不会重复报警
+        if let Err(e) = seen.save() {
+            warn!(error = format!("{:#}", e), "⚠️ 保存去重记录失败");
         }
+        // 等正在处理的签名都交还 worker (拉取、检测、发送报警)；已经在等最终确认的不再等
+        let _ = workers.acquire_many(consumer_config.concurrency.workers as u32).await;
+        info!("👋 队列里的签名已经处理完");
     });
 
-    // --- 前端生产者 ---
-    println!("📡 连接 WebSocket...");
-    let pubsub_client = PubsubClient::new(&ws_url).await?;
-    let filter = RpcTransactionLogsFilter::Mentions(vec!["11111111111111111111111111111111".to_string()]);
-    let config = RpcTransactionLogsConfig {
-        commitment: Some(CommitmentConfig::processed()),
+    // --- 前端生产者 (断线自动重连) ---
+    let result = tokio::select! {
+        result = feed::run(config, rpc_client, watchlist, queue.clone(), providers) => result,
+        () = shutdown_signal() => {
+            info!("🛑 收到退出信号，处理完队列里剩下的签名后退出...");
+            Ok(())
+        }
     };
-    let (mut stream, _unsub) = pubsub_client.logs_subscribe(filter, config).await?;
-
-    println!("🎧 监听中... (等待巨鲸出现)");
-
-    while let Some(response) = stream.next().await {
-        let logs = response.value;
-        if logs.err.is_some() { continue; }
-        if let Err(_) = tx.send(logs.signature.clone()).await { break; }
-    }
-
-    Ok(())
+    // 订阅端退出后关闭队列，消费者取完剩下的签名就停
+    queue.close();
+    consumer.await.context("后台消费者异常退出")?;
+    result
 }
 
-async fn process_transaction(client: Arc<RpcClient>, signature_str: String) -> anyhow::Result<()> {
-    let signature = Signature::from_str(&signature_str)?;
-    let tx_detail = client.get_transaction(&signature, UiTransactionEncoding::JsonParsed).await;
-
-    if let Ok(tx) = tx_detail {
-        if let Some(meta) = tx.transaction.meta {
-            if meta.pre_balances.len() == 0 || meta.post_balances.len() == 0 { return Ok(()); }
-
-            let pre_bal = meta.pre_balances[0];
-            let post_bal = meta.post_balances[0];
-            let diff_lamports = (pre_bal as i64 - post_bal as i64).abs();
-            let sol_amount = diff_lamports as f64 / 1_000_000_000.0;
-
-            // 为了测试，我们可以把阈值设低一点，比如 0.1 SOL
-            if sol_amount > 0.1 {
-                let msg = format!(
-                    "🐋 <b>巨鲸警报!</b>\n\n💰 <b>金额:</b> {:.2} SOL\n🔗 <a href=\"https://solscan.io/tx/{}\">查看交易详情</a>\n📉 余额变化: {:.2} -> {:.2}",
-                    sol_amount, signature_str, 
-                    pre_bal as f64 / 1e9, post_bal as f64 / 1e9
-                );
-
-                println!("--------\n{}\n--------", msg); // 终端也打印一份
-
-                // 🔥 发送报警 (Fire and forget: 不用等它发送成功，发出去就行)
-                // 这里我们不需要 .await? 阻塞当前函数，但因为我们需要它是异步的，
-                // 所以直接调用，让它在当前任务里跑完即可。
-                send_telegram_alert(msg).await;
-            }
+// Ctrl-C，以及 Kubernetes 停止容器时发的 SIGTERM
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        let mut term = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()).expect("无法监听 SIGTERM");
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = term.recv() => {}
         }
     }
-    Ok(())
+    #[cfg(not(unix))]
+    let _ = tokio::signal::ctrl_c().await;
 }
 
-// --- 5. 新增：Telegram 报警模块 ---
-async fn send_telegram_alert(message: String) {
-    let token = match env::var("TELEGRAM_TOKEN") {
-        Ok(t) => t,
-        Err(_) => return,
-    };
-    let chat_id = match env::var("TELEGRAM_CHAT_ID") {
-        Ok(id) => id,
-        Err(_) => return,
+async fn process_transaction(
+    client: Arc<RpcClient>,
+    config: Arc<Config>,
+    dispatcher: Arc<Dispatcher>,
+    watchlist: Arc<Watchlist>,
+    retry: Arc<RetryQueue>,
+    pending: Pending,
+    permit: OwnedSemaphorePermit,
+) -> anyhow::Result<()> {
+    let signature = Signature::from_str(&pending.observed.signature).context("签名格式错误")?;
+    let commitment = config.subscription.commitment;
+    let tx = match fetch::fetch_transaction(&client, &signature, commitment.fetch_config()).await {
+        Ok(Some(tx)) => tx,
+        Ok(None) => {
+            METRICS.processed.with_label_values(&["not_found"]).inc();
+            retry.retry(pending, Reason::NotFound);
+            return Ok(());
+        }
+        Err(e) if fetch::is_rpc_error(&e) => {
+            METRICS.processed.with_label_values(&["rpc_error"]).inc();
+            retry.retry(pending, Reason::RpcError(e));
+            return Ok(());
+        }
+        Err(e) => return Err(e.context("解析交易失败")),
     };
+    retry.succeeded(&pending);
 
-    let url = format!("https://api.telegram.org/bot{}/sendMessage", token);
+    let Some(mut event) = WhaleEvent::detect(&tx, &watchlist.thresholds()) else {
+        METRICS.processed.with_label_values(&["no_alert"]).inc();
+        return Ok(());
+    };
+    if matches!(commitment, config::Commitment::Finalized) {
+        event.status = TxStatus::Finalized;
+    }
+    event.feed = pending.observed.feed;
+    // 终端也打印一份
+    info!(severity = ?event.severity, "🐋 发现巨鲸\n--------\n{}\n--------", dispatcher.preview(&event));
 
-    // 打印调试信息：看看我们到底发了什么 ID
-    // println!("DEBUG: 正在发送给 Chat ID: '{}'", chat_id); 
+    if watchlist.is_muted() {
+        info!("🔕 静音中，跳过报警");
+        METRICS.processed.with_label_values(&["muted"]).inc();
+        return Ok(());
+    }
+    METRICS.processed.with_label_values(&["alert"]).inc();
+    for rule in &event.rules {
+        METRICS.alerts.with_label_values(&[rule]).inc();
+    }
 
-    let params = serde_json::json!({
-        "chat_id": chat_id, // 这里的 chat_id 如果包含空格或换行符会导致 400
-        "text": message,
-        "parse_mode": "HTML",
-        "disable_web_page_preview": true
-    });
+    // 🔥 分发给所有匹配的报警通道，各通道并发发送、失败互不影响
+    let updatable = dispatcher.dispatch(&event).await;
 
-    // 强制使用你设置的代理端口 (根据你之前的命令是 7897)
-    let proxy = reqwest::Proxy::all("http://127.0.0.1:7897").unwrap();
-    let client = reqwest::Client::builder()
-        .proxy(proxy)
-        .build()
-        .unwrap_or_else(|_| reqwest::Client::new());
-    
-    match client.post(url).json(&params).send().await {
-        Ok(res) => {
-            if !res.status().is_success() {
-                eprintln!("⚠️ Telegram 发送失败: Status {}", res.status());
-                // 🔥 新增：打印具体的错误响应体，这能告诉我们到底是哪里错了
-                if let Ok(text) = res.text().await {
-                    eprintln!("❌ 错误原因: {}", text);
-                }
-            } else {
-                println!("✅ Telegram 报警发送成功!");
-            }
-        },
-        Err(e) => eprintln!("⚠️ Telegram 网络错误: {}", e),
+    // 先按 confirmed 报警，等到最终状态后再回头修改已经发出的消息
+    // 等待最终确认要一两分钟，期间让出 worker；没有能修改消息的通道发出过报警就不用等
+    drop(permit);
+    if updatable && event.status == TxStatus::Confirmed {
+        event.status = finality::wait_for_finality(&client, &signature, &config.finality).await;
+        info!(status = ?event.status, "{} 最终状态", event.status.badge());
+        dispatcher.update(&event).await;
     }
-}
\ No newline at end of file
+    Ok(())
+}
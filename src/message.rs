@@ -0,0 +1,363 @@
+use crate::balance::short_address;
+use crate::config::{MessageOptions, MessagesConfig};
+use crate::event::{TxStatus, WhaleEvent};
+use anyhow::Context;
+use minijinja::Environment;
+use serde::{Deserialize, Serialize};
+use solana_sdk::native_token::lamports_to_sol;
+use solana_sdk::pubkey::Pubkey;
+
+// 自定义模板在环境里的名字
+const TEMPLATE_NAME: &str = "alert";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    Zh,
+    En,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+pub enum Explorer {
+    #[default]
+    #[serde(rename = "solscan")]
+    Solscan,
+    #[serde(rename = "solana_explorer")]
+    Solana,
+    #[serde(rename = "solanafm")]
+    SolanaFm,
+    #[serde(rename = "xray")]
+    Xray,
+}
+
+impl Explorer {
+    pub fn tx_url(self, signature: &str) -> String {
+        match self {
+            Explorer::Solscan => format!("https://solscan.io/tx/{}", signature),
+            Explorer::Solana => format!("https://explorer.solana.com/tx/{}", signature),
+            Explorer::SolanaFm => format!("https://solana.fm/tx/{}", signature),
+            Explorer::Xray => format!("https://xray.helius.xyz/tx/{}", signature),
+        }
+    }
+
+    pub fn account_url(self, address: &Pubkey) -> String {
+        match self {
+            Explorer::Solscan => format!("https://solscan.io/account/{}", address),
+            Explorer::Solana => format!("https://explorer.solana.com/address/{}", address),
+            Explorer::SolanaFm => format!("https://solana.fm/address/{}", address),
+            Explorer::Xray => format!("https://xray.helius.xyz/account/{}", address),
+        }
+    }
+}
+
+// 各个通道的固定文案，模板里通过 t.xxx 使用
+#[derive(Debug, Serialize)]
+pub struct Strings {
+    pub title: &'static str,
+    pub amount: &'static str,
+    pub flows: &'static str,
+    pub fee: &'static str,
+    pub payer: &'static str,
+    pub transaction: &'static str,
+    pub view_tx: &'static str,
+    pub memo: &'static str,
+    pub unknown_token: &'static str,
+    pub confirmed: &'static str,
+    pub finalized: &'static str,
+    pub dropped: &'static str,
+}
+
+const ZH: Strings = Strings {
+    title: "巨鲸警报!",
+    amount: "金额",
+    flows: "资金流向",
+    fee: "手续费",
+    payer: "付款人",
+    transaction: "交易",
+    view_tx: "查看交易详情",
+    memo: "备注",
+    unknown_token: "未知代币",
+    confirmed: "🟡 已确认",
+    finalized: "🟢 已最终确认",
+    dropped: "🔴 已丢弃 (未上链)",
+};
+
+const EN: Strings = Strings {
+    title: "Whale Alert!",
+    amount: "Amount",
+    flows: "Flows",
+    fee: "Fee",
+    payer: "payer",
+    transaction: "Transaction",
+    view_tx: "View transaction",
+    memo: "Memo",
+    unknown_token: "Unknown token",
+    confirmed: "🟡 Confirmed",
+    finalized: "🟢 Finalized",
+    dropped: "🔴 Dropped (never landed)",
+};
+
+impl Locale {
+    pub fn strings(self) -> &'static Strings {
+        match self {
+            Locale::Zh => &ZH,
+            Locale::En => &EN,
+        }
+    }
+}
+
+impl Strings {
+    pub fn status(&self, status: TxStatus) -> &'static str {
+        match status {
+            TxStatus::Confirmed => self.confirmed,
+            TxStatus::Finalized => self.finalized,
+            TxStatus::Dropped => self.dropped,
+        }
+    }
+}
+
+// 模板输出的格式，决定事件字段 (备注、代币名这类不可信的内容) 怎么转义
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Markup {
+    // Telegram 的 HTML parse_mode
+    Html,
+    // Discord
+    Markdown,
+    // Slack 的 mrkdwn
+    Mrkdwn,
+}
+
+impl Markup {
+    pub fn escape(self, text: &str) -> String {
+        match self {
+            Markup::Html => text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;"),
+            Markup::Markdown => {
+                let mut escaped = String::with_capacity(text.len());
+                for c in text.chars() {
+                    if matches!(c, '\\' | '*' | '_' | '~' | '`' | '|' | '>' | '[' | ']' | '(' | ')' | '#' | '-') {
+                        escaped.push('\\');
+                    }
+                    escaped.push(c);
+                }
+                escaped
+            }
+            // mrkdwn 只需要转义这三个字符
+            Markup::Mrkdwn => text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;"),
+        }
+    }
+}
+
+// --- 每个通道一个：语言、区块浏览器，以及可选的自定义模板 ---
+pub struct Renderer {
+    pub locale: Locale,
+    pub explorer: Explorer,
+    markup: Markup,
+    template: Option<Environment<'static>>,
+}
+
+impl Renderer {
+    // fallback 是通道自带的默认模板，没有时由通道自己拼消息
+    pub fn new(
+        messages: &MessagesConfig,
+        options: &MessageOptions,
+        markup: Markup,
+        fallback: Option<&'static str>,
+    ) -> anyhow::Result<Self> {
+        let source = match (&options.template, &options.template_file) {
+            (Some(template), _) => Some(template.clone()),
+            (None, Some(path)) => Some(
+                std::fs::read_to_string(path).with_context(|| format!("无法读取模板文件 {}", path.display()))?,
+            ),
+            (None, None) => fallback.map(str::to_string),
+        };
+
+        let template = match source {
+            Some(source) => {
+                let mut env = Environment::new();
+                // 模板本身是可信的，只有插进去的值需要转义；加了 |safe 的值原样输出
+                env.set_formatter(move |out, _state, value| {
+                    if value.is_undefined() || value.is_none() {
+                        return Ok(());
+                    }
+                    let text = value.to_string();
+                    let text = if value.is_safe() { text } else { markup.escape(&text) };
+                    out.write_str(&text)?;
+                    Ok(())
+                });
+                env.add_template_owned(TEMPLATE_NAME, source).context("消息模板语法错误")?;
+                Some(env)
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            locale: options.locale.unwrap_or(messages.locale),
+            explorer: options.explorer.unwrap_or(messages.explorer),
+            markup,
+            template,
+        })
+    }
+
+    pub fn strings(&self) -> &'static Strings {
+        self.locale.strings()
+    }
+
+    // 没有模板时返回 None，由通道用自己的默认格式
+    pub fn render(&self, event: &WhaleEvent) -> anyhow::Result<Option<String>> {
+        let Some(env) = &self.template else { return Ok(None) };
+        let text = env
+            .get_template(TEMPLATE_NAME)?
+            .render(self.context(event))
+            .context("渲染消息模板失败")?;
+        Ok(Some(text))
+    }
+
+    // 通道自己拼消息时用来转义事件字段
+    pub fn escape(&self, text: &str) -> String {
+        self.markup.escape(text)
+    }
+
+    pub fn symbol<'a>(&self, symbol: &'a Option<String>) -> &'a str {
+        symbol.as_deref().unwrap_or(self.strings().unknown_token)
+    }
+
+    fn context(&self, event: &WhaleEvent) -> EventContext {
+        let transfer = |from: &Pubkey, to: &Pubkey, amount: f64| TransferContext {
+            from: from.to_string(),
+            to: to.to_string(),
+            from_short: short_address(from),
+            to_short: short_address(to),
+            from_url: self.explorer.account_url(from),
+            to_url: self.explorer.account_url(to),
+            amount: format!("{:.2}", amount),
+        };
+
+        EventContext {
+            signature: event.signature.clone(),
+            feed: event.feed.clone(),
+            slot: event.slot,
+            block_time: event.block_time,
+            time: event
+                .block_time
+                .and_then(|t| chrono::DateTime::from_timestamp(t, 0))
+                .map(|t| t.format("%Y-%m-%d %H:%M:%S UTC").to_string()),
+            severity: event.severity.label(),
+            status: self.strings().status(event.status),
+            fee: lamports_to_sol(event.fee).to_string(),
+            fee_payer: event.fee_payer.to_string(),
+            fee_payer_short: short_address(&event.fee_payer),
+            fee_payer_url: self.explorer.account_url(&event.fee_payer),
+            tx_url: self.explorer.tx_url(&event.signature),
+            memo: event.memo.clone(),
+            sol: event.sol.as_ref().map(|sol| SolContext {
+                amount: format!("{:.2}", sol.amount()),
+                lamports: sol.lamports,
+                transfers: sol
+                    .transfers
+                    .iter()
+                    .map(|t| transfer(&t.from, &t.to, lamports_to_sol(t.lamports)))
+                    .collect(),
+            }),
+            tokens: event
+                .tokens
+                .iter()
+                .map(|token| TokenContext {
+                    mint: token.mint.clone(),
+                    symbol: self.symbol(&token.symbol).to_string(),
+                    amount: format!("{:.2}", token.amount),
+                    transfers: token.transfers.iter().map(|t| transfer(&t.from, &t.to, t.ui_amount())).collect(),
+                })
+                .collect(),
+            t: self.strings(),
+        }
+    }
+}
+
+// 模板里能用的全部字段，金额都是保留两位小数的字符串
+#[derive(Serialize)]
+struct EventContext {
+    signature: String,
+    feed: String,
+    slot: u64,
+    block_time: Option<i64>,
+    time: Option<String>,
+    severity: &'static str,
+    status: &'static str,
+    fee: String,
+    fee_payer: String,
+    fee_payer_short: String,
+    fee_payer_url: String,
+    tx_url: String,
+    memo: Option<String>,
+    sol: Option<SolContext>,
+    tokens: Vec<TokenContext>,
+    t: &'static Strings,
+}
+
+#[derive(Serialize)]
+struct SolContext {
+    amount: String,
+    lamports: u64,
+    transfers: Vec<TransferContext>,
+}
+
+#[derive(Serialize)]
+struct TokenContext {
+    mint: String,
+    symbol: String,
+    amount: String,
+    transfers: Vec<TransferContext>,
+}
+
+#[derive(Serialize)]
+struct TransferContext {
+    from: String,
+    to: String,
+    from_short: String,
+    to_short: String,
+    from_url: String,
+    to_url: String,
+    amount: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ThresholdConfig;
+    use crate::fetch::FetchedTransaction;
+
+    fn event_with_memo(memo: &str) -> WhaleEvent {
+        let json = include_str!("../tests/fixtures/legacy_transfer.json");
+        let tx = FetchedTransaction::parse(serde_json::from_str(json).unwrap()).unwrap();
+        let mut event = WhaleEvent::detect(&tx, &ThresholdConfig::default()).unwrap();
+        event.memo = Some(memo.to_string());
+        event
+    }
+
+    #[test]
+    fn escapes_untrusted_fields() {
+        let event = event_with_memo("<b>gm</b> & *wagmi*");
+        let options = MessageOptions {
+            template: Some("{{ t.memo }}: {{ memo }} | {{ '<i>ok</i>'|safe }} | {{ tx_url }}".to_string()),
+            locale: Some(Locale::En),
+            explorer: Some(Explorer::Xray),
+            ..Default::default()
+        };
+
+        let html = Renderer::new(&MessagesConfig::default(), &options, Markup::Html, None).unwrap();
+        assert_eq!(
+            html.render(&event).unwrap().unwrap(),
+            format!("Memo: &lt;b&gt;gm&lt;/b&gt; &amp; *wagmi* | <i>ok</i> | https://xray.helius.xyz/tx/{}", event.signature)
+        );
+
+        let markdown = Renderer::new(&MessagesConfig::default(), &options, Markup::Markdown, None).unwrap();
+        assert!(markdown.render(&event).unwrap().unwrap().starts_with(r"Memo: <b\>gm</b\> & \*wagmi\* |"));
+    }
+
+    #[test]
+    fn bad_template_fails_early() {
+        let options = MessageOptions { template: Some("{% if %}".to_string()), ..Default::default() };
+        assert!(Renderer::new(&MessagesConfig::default(), &options, Markup::Html, None).is_err());
+    }
+}
@@ -0,0 +1,87 @@
+use prometheus::{Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts, Registry, TextEncoder};
+use std::sync::LazyLock;
+
+// --- Prometheus 指标：全局一份，各模块在事情发生的地方直接记，/metrics 读出来 ---
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+// RPC 请求耗时的分桶 (秒)
+const RPC_LATENCY_BUCKETS: &[f64] = &[0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+pub struct Metrics {
+    registry: Registry,
+    // 每个 WebSocket 节点、每一路订阅推送来的签名数，包括其他节点已经送过的
+    pub notifications: IntCounterVec,
+    // 签名的处理结果: alert / no_alert / muted / not_found / rpc_error / error，重试的签名每次都算
+    pub processed: IntCounterVec,
+    pub rpc_latency: HistogramVec,
+    pub rpc_errors: IntCounterVec,
+    // 拉交易的重试: not_found / rpc_error 是排队重试，recovered / abandoned 是最终结果
+    pub fetch_retries: IntCounterVec,
+    // 触发报警的阈值: sol / wallet / token:<symbol>，一个事件可能同时触发几个
+    pub alerts: IntCounterVec,
+    pub sink_sent: IntCounterVec,
+    pub sink_failures: IntCounterVec,
+    pub queue_depth: IntGauge,
+    pub queue_dropped: IntCounter,
+    // 原因: closed / stalled / error / watchlist
+    pub ws_reconnects: IntCounterVec,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+        Self {
+            notifications: counter_vec(&registry, "whale_notifications_total", "WebSocket 推送的签名数", &["feed", "provider"]),
+            processed: counter_vec(&registry, "whale_signatures_processed_total", "签名的处理结果", &["outcome"]),
+            rpc_latency: histogram_vec(&registry, "whale_rpc_request_duration_seconds", "RPC 请求耗时", &["endpoint", "method"]),
+            rpc_errors: counter_vec(&registry, "whale_rpc_errors_total", "RPC 请求失败次数", &["endpoint", "method"]),
+            fetch_retries: counter_vec(&registry, "whale_fetch_retries_total", "拉交易的重试", &["reason"]),
+            alerts: counter_vec(&registry, "whale_alerts_total", "按触发阈值统计的报警数", &["rule"]),
+            sink_sent: counter_vec(&registry, "whale_sink_alerts_total", "每个通道发送成功的报警数", &["sink"]),
+            sink_failures: counter_vec(&registry, "whale_sink_failures_total", "每个通道发送 / 更新失败的次数", &["sink", "operation"]),
+            queue_depth: register(&registry, IntGauge::new("whale_queue_depth", "处理队列里等待的签名数")),
+            queue_dropped: register(&registry, IntCounter::new("whale_queue_dropped_total", "队列满了被丢掉的签名数")),
+            ws_reconnects: counter_vec(&registry, "whale_ws_reconnects_total", "WebSocket 重连次数", &["provider", "reason"]),
+            registry,
+        }
+    }
+
+    // Prometheus 文本格式
+    pub fn render(&self) -> String {
+        let mut buf = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buf).expect("写入内存不会失败");
+        String::from_utf8(buf).expect("文本格式是 UTF-8")
+    }
+}
+
+fn counter_vec(registry: &Registry, name: &str, help: &str, labels: &[&str]) -> IntCounterVec {
+    register(registry, IntCounterVec::new(Opts::new(name, help), labels))
+}
+
+fn histogram_vec(registry: &Registry, name: &str, help: &str, labels: &[&str]) -> HistogramVec {
+    let opts = HistogramOpts::new(name, help).buckets(RPC_LATENCY_BUCKETS.to_vec());
+    register(registry, HistogramVec::new(opts, labels))
+}
+
+// 指标名和标签都是写死的，出错只可能是代码写错了
+fn register<M: prometheus::core::Collector + Clone + 'static>(registry: &Registry, metric: prometheus::Result<M>) -> M {
+    let metric = metric.expect("指标定义合法");
+    registry.register(Box::new(metric.clone())).expect("指标没有重名");
+    metric
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_labeled_metrics() {
+        METRICS.alerts.with_label_values(&["token:USDC"]).inc();
+        METRICS.rpc_latency.with_label_values(&["helius", "getTransaction"]).observe(0.2);
+
+        let text = METRICS.render();
+        assert!(text.contains(r#"whale_alerts_total{rule="token:USDC"}"#), "{}", text);
+        assert!(text.contains(r#"whale_rpc_request_duration_seconds_bucket{endpoint="helius",method="getTransaction",le="0.25"}"#), "{}", text);
+        assert!(text.contains("# TYPE whale_queue_depth gauge"), "{}", text);
+    }
+}
@@ -0,0 +1,263 @@
+use crate::config::{OverflowPolicy, WALLET_FEED};
+use crate::feed::Observed;
+use crate::metrics::METRICS;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::Notify;
+use tracing::warn;
+
+// 丢弃时不是每条都打日志，第一条和之后每隔这么多条打一次
+const DROP_LOG_EVERY: u64 = 1000;
+
+#[derive(Debug, Default)]
+pub struct QueueStats {
+    pub enqueued: AtomicU64,
+    pub dropped: AtomicU64,
+}
+
+#[derive(Default)]
+struct Inner {
+    // prioritize_watched 时关注钱包的签名放这里，优先处理；其他策略下不用
+    watched: VecDeque<Observed>,
+    normal: VecDeque<Observed>,
+    closed: bool,
+}
+
+impl Inner {
+    fn len(&self) -> usize {
+        self.watched.len() + self.normal.len()
+    }
+}
+
+// --- 订阅端和处理端之间的有界队列，满了以后按 policy 处理 ---
+// 每个 WebSocket 节点都是一个生产者。等待方先登记 Notified 再检查队列状态，
+// 检查之后、开始等待之前发生的 notify 也能收到；每出队一个空位唤醒一个等空位的生产者，
+// 关闭时用 notify_waiters 唤醒全部
+pub struct SignatureQueue {
+    capacity: usize,
+    policy: OverflowPolicy,
+    inner: Mutex<Inner>,
+    item_ready: Notify,
+    space_ready: Notify,
+    pub stats: QueueStats,
+}
+
+impl SignatureQueue {
+    pub fn new(capacity: usize, policy: OverflowPolicy) -> Self {
+        Self {
+            capacity,
+            policy,
+            inner: Mutex::new(Inner::default()),
+            item_ready: Notify::new(),
+            space_ready: Notify::new(),
+            stats: QueueStats::default(),
+        }
+    }
+
+    // 队列已经关闭时返回 false；block 策略下满了会一直等到有空位
+    pub async fn push(&self, observed: Observed) -> bool {
+        let mut observed = Some(observed);
+        loop {
+            let space = self.space_ready.notified();
+            tokio::pin!(space);
+            space.as_mut().enable();
+            {
+                let mut inner = self.inner.lock().unwrap();
+                if inner.closed {
+                    return false;
+                }
+                let item = observed.take().expect("只在等待空位后重试");
+                if inner.len() < self.capacity {
+                    self.enqueue(&mut inner, item);
+                    return true;
+                }
+                match self.policy {
+                    OverflowPolicy::Block => observed = Some(item),
+                    OverflowPolicy::DropNewest => {
+                        self.dropped(&item);
+                        return true;
+                    }
+                    OverflowPolicy::DropOldest => {
+                        if let Some(oldest) = inner.normal.pop_front() {
+                            self.dropped(&oldest);
+                        }
+                        self.enqueue(&mut inner, item);
+                        return true;
+                    }
+                    // 新来的是关注钱包的：挤掉最早的普通签名，没有普通签名时挤掉最早的关注签名
+                    // 新来的是普通签名：直接丢掉
+                    OverflowPolicy::PrioritizeWatched => {
+                        if item.feed != WALLET_FEED {
+                            self.dropped(&item);
+                            return true;
+                        }
+                        if let Some(oldest) = inner.normal.pop_front().or_else(|| inner.watched.pop_front()) {
+                            self.dropped(&oldest);
+                        }
+                        self.enqueue(&mut inner, item);
+                        return true;
+                    }
+                }
+            }
+            space.await;
+        }
+    }
+
+    // 关闭并且取空之后返回 None
+    pub async fn pop(&self) -> Option<Observed> {
+        loop {
+            let item = self.item_ready.notified();
+            tokio::pin!(item);
+            item.as_mut().enable();
+            {
+                let mut inner = self.inner.lock().unwrap();
+                if let Some(item) = inner.watched.pop_front().or_else(|| inner.normal.pop_front()) {
+                    METRICS.queue_depth.set(inner.len() as i64);
+                    self.space_ready.notify_one();
+                    return Some(item);
+                }
+                if inner.closed {
+                    return None;
+                }
+            }
+            item.await;
+        }
+    }
+
+    pub fn close(&self) {
+        self.inner.lock().unwrap().closed = true;
+        self.item_ready.notify_waiters();
+        self.space_ready.notify_waiters();
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.inner.lock().unwrap().closed
+    }
+
+    pub fn depth(&self) -> usize {
+        self.inner.lock().unwrap().len()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    fn enqueue(&self, inner: &mut Inner, item: Observed) {
+        if self.policy == OverflowPolicy::PrioritizeWatched && item.feed == WALLET_FEED {
+            inner.watched.push_back(item);
+        } else {
+            inner.normal.push_back(item);
+        }
+        self.stats.enqueued.fetch_add(1, Ordering::Relaxed);
+        METRICS.queue_depth.set(inner.len() as i64);
+        self.item_ready.notify_one();
+    }
+
+    fn dropped(&self, item: &Observed) {
+        let dropped = self.stats.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+        METRICS.queue_dropped.inc();
+        if dropped % DROP_LOG_EVERY == 1 {
+            warn!(policy = ?self.policy, signature = %item.signature, dropped, "🚮 处理队列已满，丢弃签名");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn observed(signature: &str, feed: &str) -> Observed {
+        Observed { signature: signature.to_string(), feed: feed.to_string() }
+    }
+
+    async fn drain(queue: &SignatureQueue) -> Vec<String> {
+        let mut out = Vec::new();
+        while queue.depth() > 0 {
+            out.push(queue.pop().await.unwrap().signature);
+        }
+        out
+    }
+
+    #[tokio::test]
+    async fn overflow_policies() {
+        let queue = SignatureQueue::new(2, OverflowPolicy::DropOldest);
+        for sig in ["a", "b", "c"] {
+            assert!(queue.push(observed(sig, "mention")).await);
+        }
+        assert_eq!(drain(&queue).await, vec!["b", "c"]);
+        assert_eq!(queue.stats.dropped.load(Ordering::Relaxed), 1);
+
+        let queue = SignatureQueue::new(2, OverflowPolicy::DropNewest);
+        for sig in ["a", "b", "c"] {
+            assert!(queue.push(observed(sig, "mention")).await);
+        }
+        assert_eq!(drain(&queue).await, vec!["a", "b"]);
+
+        // 关注钱包的签名挤掉普通签名，并且先出队
+        let queue = SignatureQueue::new(2, OverflowPolicy::PrioritizeWatched);
+        queue.push(observed("a", "mention")).await;
+        queue.push(observed("b", "mention")).await;
+        queue.push(observed("w", WALLET_FEED)).await;
+        queue.push(observed("c", "mention")).await;
+        assert_eq!(drain(&queue).await, vec!["w", "b"]);
+        assert_eq!(queue.stats.dropped.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn block_waits_for_space() {
+        let queue = Arc::new(SignatureQueue::new(1, OverflowPolicy::Block));
+        queue.push(observed("a", "mention")).await;
+
+        let producer = tokio::spawn({
+            let queue = queue.clone();
+            async move { queue.push(observed("b", "mention")).await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!producer.is_finished());
+
+        assert_eq!(queue.pop().await.unwrap().signature, "a");
+        assert!(producer.await.unwrap());
+        assert_eq!(queue.pop().await.unwrap().signature, "b");
+        assert_eq!(queue.stats.dropped.load(Ordering::Relaxed), 0);
+
+        queue.close();
+        assert!(queue.pop().await.is_none());
+        assert!(!queue.push(observed("c", "mention")).await);
+    }
+
+    #[tokio::test]
+    async fn wakes_every_blocked_producer() {
+        let queue = Arc::new(SignatureQueue::new(1, OverflowPolicy::Block));
+        queue.push(observed("a", "mention")).await;
+        let push = |sig: &'static str| {
+            let queue = queue.clone();
+            tokio::spawn(async move { queue.push(observed(sig, "helius")).await })
+        };
+
+        // 多个节点同时等空位，每出队一个放进来一个
+        let producers = [push("b"), push("c"), push("d")];
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let mut popped = Vec::new();
+        for _ in 0..4 {
+            popped.push(queue.pop().await.unwrap().signature);
+        }
+        popped.sort();
+        assert_eq!(popped, vec!["a", "b", "c", "d"]);
+        for producer in producers {
+            assert!(producer.await.unwrap());
+        }
+
+        // 关闭时所有还在等的生产者都要返回
+        queue.push(observed("e", "mention")).await;
+        let producers = [push("f"), push("g")];
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        queue.close();
+        for producer in producers {
+            let pushed = tokio::time::timeout(Duration::from_secs(1), producer).await.unwrap().unwrap();
+            assert!(!pushed);
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use crate::config::RetryConfig;
+use crate::feed::Observed;
+use crate::metrics::METRICS;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+// 一次拉取任务，attempt 从 1 开始
+#[derive(Clone, Debug)]
+pub struct Pending {
+    pub observed: Observed,
+    pub attempt: u32,
+}
+
+impl Pending {
+    pub fn new(observed: Observed) -> Self {
+        Self { observed, attempt: 1 }
+    }
+}
+
+// 这次没拉到的原因
+pub enum Reason {
+    // 节点还没有这笔交易，processed 阶段的签名很常见
+    NotFound,
+    // RPC 本身出错：网络、限流、节点异常
+    RpcError(anyhow::Error),
+}
+
+#[derive(Debug, Default)]
+pub struct RetryStats {
+    pub not_found: AtomicU64,
+    pub rpc_errors: AtomicU64,
+    // 重试后拉到了
+    pub recovered: AtomicU64,
+    // 次数用完仍然没拉到
+    pub abandoned: AtomicU64,
+}
+
+// --- 延迟重试队列：到点后从返回的 receiver 里重新交给处理端 ---
+pub struct RetryQueue {
+    max_attempts: u32,
+    initial_delay: Duration,
+    max_delay: Duration,
+    tx: mpsc::UnboundedSender<Pending>,
+    pub stats: RetryStats,
+}
+
+impl RetryQueue {
+    pub fn new(config: &RetryConfig) -> (Self, mpsc::UnboundedReceiver<Pending>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let queue = Self {
+            max_attempts: config.max_attempts,
+            initial_delay: Duration::from_millis(config.initial_delay_ms),
+            max_delay: Duration::from_millis(config.max_delay_ms),
+            tx,
+            stats: RetryStats::default(),
+        };
+        (queue, rx)
+    }
+
+    // 第 attempt 次失败后等多久：initial, 2 × initial, 4 × initial ... 封顶 max
+    fn delay(&self, attempt: u32) -> Duration {
+        let factor = 1u32 << (attempt - 1).min(16);
+        (self.initial_delay * factor).min(self.max_delay)
+    }
+
+    pub fn retry(&self, pending: Pending, reason: Reason) {
+        // 签名在调用方的 span 上
+        match &reason {
+            Reason::NotFound => {
+                METRICS.fetch_retries.with_label_values(&["not_found"]).inc();
+                self.stats.not_found.fetch_add(1, Ordering::Relaxed)
+            }
+            Reason::RpcError(e) => {
+                warn!(attempt = pending.attempt, error = format!("{:#}", e), "⚠️ 拉取交易出错");
+                METRICS.fetch_retries.with_label_values(&["rpc_error"]).inc();
+                self.stats.rpc_errors.fetch_add(1, Ordering::Relaxed)
+            }
+        };
+
+        if pending.attempt >= self.max_attempts {
+            METRICS.fetch_retries.with_label_values(&["abandoned"]).inc();
+            let abandoned = self.stats.abandoned.fetch_add(1, Ordering::Relaxed) + 1;
+            let why = match reason {
+                Reason::NotFound => "节点上仍然没有这笔交易",
+                Reason::RpcError(_) => "RPC 一直出错",
+            };
+            warn!(attempts = pending.attempt, abandoned, "🗑️ 放弃交易: {}", why);
+            return;
+        }
+
+        let delay = self.delay(pending.attempt);
+        let tx = self.tx.clone();
+        let next = Pending { attempt: pending.attempt + 1, ..pending };
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            let _ = tx.send(next);
+        });
+    }
+
+    // 拉取成功时调用，只统计经过重试才拉到的
+    pub fn succeeded(&self, pending: &Pending) {
+        if pending.attempt > 1 {
+            METRICS.fetch_retries.with_label_values(&["recovered"]).inc();
+            let recovered = self.stats.recovered.fetch_add(1, Ordering::Relaxed) + 1;
+            info!(attempt = pending.attempt, recovered, "♻️ 重试后拉取成功");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending() -> Pending {
+        Pending::new(Observed { signature: "sig".to_string(), feed: "mention".to_string() })
+    }
+
+    #[tokio::test]
+    async fn retries_until_abandoned() {
+        let config = RetryConfig { max_attempts: 3, initial_delay_ms: 10, max_delay_ms: 15 };
+        let (queue, mut rx) = RetryQueue::new(&config);
+        assert_eq!(queue.delay(1), Duration::from_millis(10));
+        assert_eq!(queue.delay(2), Duration::from_millis(15));
+
+        queue.retry(pending(), Reason::NotFound);
+        let second = rx.recv().await.unwrap();
+        assert_eq!(second.attempt, 2);
+        queue.retry(second, Reason::RpcError(anyhow::anyhow!("429 Too Many Requests")));
+        let third = rx.recv().await.unwrap();
+        assert_eq!(third.attempt, 3);
+
+        // 第 3 次是最后一次，不再排队
+        queue.retry(third.clone(), Reason::NotFound);
+        assert_eq!(queue.stats.abandoned.load(Ordering::Relaxed), 1);
+        assert_eq!(queue.stats.not_found.load(Ordering::Relaxed), 2);
+        assert_eq!(queue.stats.rpc_errors.load(Ordering::Relaxed), 1);
+
+        queue.succeeded(&third);
+        queue.succeeded(&pending());
+        assert_eq!(queue.stats.recovered.load(Ordering::Relaxed), 1);
+        drop(queue);
+        assert!(rx.recv().await.is_none());
+    }
+}
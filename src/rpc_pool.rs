@@ -0,0 +1,416 @@
+use crate::config::{RpcConfig, RpcEndpointConfig};
+use crate::metrics::METRICS;
+use async_trait::async_trait;
+use serde::Serialize;
+use solana_client::client_error::{ClientError, ClientErrorKind, Result as ClientResult};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_client::RpcClientConfig;
+use solana_client::rpc_custom_error::JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY;
+use solana_client::rpc_request::{RpcError, RpcRequest};
+use solana_client::rpc_sender::{RpcSender, RpcTransportStats};
+use solana_rpc_client::http_sender::HttpSender;
+use solana_sdk::commitment_config::CommitmentConfig;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+struct Endpoint {
+    label: String,
+    weight: i64,
+    sender: HttpSender,
+    // 按 max_rps 算出的请求间隔，以及下一个请求最早能发的时间
+    interval: Option<Duration>,
+    next_slot: tokio::sync::Mutex<Instant>,
+    circuit: Mutex<Circuit>,
+}
+
+#[derive(Default)]
+struct Circuit {
+    // 连续失败次数，成功一次清零
+    failures: u32,
+    state: CircuitState,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum CircuitState {
+    #[default]
+    Closed,
+    // 熔断到什么时候，过了之后放一个请求试探
+    Open(Instant),
+    // 试探请求还没回来，期间其他请求都不发到这个节点
+    Probing,
+}
+
+impl Endpoint {
+    fn available(&self, now: Instant) -> bool {
+        match self.circuit.lock().unwrap().state {
+            CircuitState::Closed => true,
+            CircuitState::Open(until) => until <= now,
+            CircuitState::Probing => false,
+        }
+    }
+
+    async fn throttle(&self) {
+        let Some(interval) = self.interval else { return };
+        let mut next = self.next_slot.lock().await;
+        let now = Instant::now();
+        if *next > now {
+            tokio::time::sleep_until((*next).into()).await;
+        }
+        *next = (*next).max(now) + interval;
+    }
+}
+
+struct Inner {
+    endpoints: Vec<Endpoint>,
+    failure_threshold: u32,
+    cooldown: Duration,
+    // 平滑加权轮询 (和 nginx 一样) 每个节点的当前权重
+    current: Mutex<Vec<i64>>,
+}
+
+impl Inner {
+    // 这次请求依次尝试的节点：加权轮询选出的排第一，其余可用的按权重排在后面，
+    // 熔断中的放在最后 —— 全部熔断时也还是要试一下，总比直接失败好；正在试探的节点不参与
+    fn order(&self, now: Instant) -> Vec<usize> {
+        let (available, open): (Vec<usize>, Vec<usize>) = (0..self.endpoints.len())
+            .filter(|&i| self.endpoints[i].circuit.lock().unwrap().state != CircuitState::Probing)
+            .partition(|&i| self.endpoints[i].available(now));
+        let mut order = Vec::with_capacity(self.endpoints.len());
+        if let Some(first) = self.pick(&available) {
+            order.push(first);
+        }
+        let mut rest: Vec<usize> = available.into_iter().filter(|i| !order.contains(i)).collect();
+        rest.sort_by_key(|&i| std::cmp::Reverse(self.endpoints[i].weight));
+        order.extend(rest);
+        order.extend(open);
+        order
+    }
+
+    fn pick(&self, candidates: &[usize]) -> Option<usize> {
+        let mut current = self.current.lock().unwrap();
+        let total: i64 = candidates.iter().map(|&i| self.endpoints[i].weight).sum();
+        let mut best: Option<usize> = None;
+        for &i in candidates {
+            current[i] += self.endpoints[i].weight;
+            if best.is_none_or(|b| current[i] > current[b]) {
+                best = Some(i);
+            }
+        }
+        let best = best?;
+        current[best] -= total;
+        Some(best)
+    }
+
+    // 发请求前调用：熔断到期的节点由这次请求独占试探，返回的 Probe 在请求结束前一直占着；
+    // 别的请求已经在试探时返回 None，跳过这个节点
+    fn admit(&self, i: usize) -> Option<Option<Probe<'_>>> {
+        let mut circuit = self.endpoints[i].circuit.lock().unwrap();
+        match circuit.state {
+            CircuitState::Open(until) if until <= Instant::now() => {
+                circuit.state = CircuitState::Probing;
+                Some(Some(Probe { inner: self, i }))
+            }
+            CircuitState::Probing => None,
+            _ => Some(None),
+        }
+    }
+
+    fn success(&self, i: usize) {
+        let mut circuit = self.endpoints[i].circuit.lock().unwrap();
+        if circuit.state != CircuitState::Closed {
+            info!(endpoint = %self.endpoints[i].label, "✅ RPC 节点已恢复");
+        }
+        circuit.state = CircuitState::Closed;
+        circuit.failures = 0;
+    }
+
+    // getHealth 通过只说明节点活着，不代表真实请求能成功：熔断中的节点提前到期，
+    // 放下一个真实请求试探，成功了才由 success 关闭熔断，失败的话立刻重新熔断
+    fn health_ok(&self, i: usize) {
+        let mut circuit = self.endpoints[i].circuit.lock().unwrap();
+        let now = Instant::now();
+        if let CircuitState::Open(until) = circuit.state
+            && until > now
+        {
+            info!(endpoint = %self.endpoints[i].label, "🩺 RPC 节点健康检查通过，放一个请求试探");
+            circuit.state = CircuitState::Open(now);
+        }
+    }
+
+    fn failure(&self, i: usize, e: &ClientError) {
+        let endpoint = &self.endpoints[i];
+        let mut circuit = endpoint.circuit.lock().unwrap();
+        circuit.failures += 1;
+        if circuit.failures < self.failure_threshold {
+            return;
+        }
+        let now = Instant::now();
+        if !matches!(circuit.state, CircuitState::Open(until) if until > now) {
+            warn!(
+                endpoint = %endpoint.label,
+                failures = circuit.failures,
+                cooldown_secs = self.cooldown.as_secs(),
+                error = %e,
+                "🔌 RPC 节点连续失败，熔断"
+            );
+        }
+        circuit.state = CircuitState::Open(now + self.cooldown);
+    }
+}
+
+// 试探请求占着节点；请求既没成功也没算作失败 (参数错误、调用方取消) 时交还，下一个请求接着试探
+struct Probe<'a> {
+    inner: &'a Inner,
+    i: usize,
+}
+
+impl Drop for Probe<'_> {
+    fn drop(&mut self) {
+        let mut circuit = self.inner.endpoints[self.i].circuit.lock().unwrap();
+        if circuit.state == CircuitState::Probing {
+            circuit.state = CircuitState::Open(Instant::now());
+        }
+    }
+}
+
+// /healthz 和 /readyz 里一个 RPC 节点的状态
+#[derive(Debug, Serialize)]
+pub struct EndpointStatus {
+    pub endpoint: String,
+    // 没有熔断 (或者熔断已经到期，可以试探)
+    pub available: bool,
+    pub consecutive_failures: u32,
+}
+
+// --- RPC 节点池：实现 RpcSender，上层照常使用 RpcClient ---
+#[derive(Clone)]
+pub struct RpcPool {
+    inner: Arc<Inner>,
+}
+
+impl RpcPool {
+    pub fn new(config: &RpcConfig) -> Self {
+        let timeout = Duration::from_secs(config.pool.timeout_secs);
+        let endpoints: Vec<Endpoint> = config.endpoints().iter().map(|e| endpoint(e, timeout)).collect();
+        let current = Mutex::new(vec![0; endpoints.len()]);
+        Self {
+            inner: Arc::new(Inner {
+                endpoints,
+                failure_threshold: config.pool.failure_threshold,
+                cooldown: Duration::from_secs(config.pool.cooldown_secs),
+                current,
+            }),
+        }
+    }
+
+    pub fn client(&self) -> RpcClient {
+        RpcClient::new_sender(self.clone(), RpcClientConfig::with_commitment(CommitmentConfig::default()))
+    }
+
+    pub fn status(&self) -> Vec<EndpointStatus> {
+        let now = Instant::now();
+        self.inner
+            .endpoints
+            .iter()
+            .map(|e| EndpointStatus {
+                endpoint: e.label.clone(),
+                available: e.available(now),
+                consecutive_failures: e.circuit.lock().unwrap().failures,
+            })
+            .collect()
+    }
+
+    // 定期调 getHealth：熔断中的节点恢复了可以提前试探，出问题的节点不用等真实请求失败才发现
+    pub fn spawn_health_checks(&self, interval: Duration) {
+        let pool: Weak<Inner> = Arc::downgrade(&self.inner);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                // 节点池已经不在了
+                let Some(inner) = pool.upgrade() else { return };
+                for (i, endpoint) in inner.endpoints.iter().enumerate() {
+                    match endpoint.sender.send(RpcRequest::GetHealth, serde_json::Value::Null).await {
+                        Ok(_) => inner.health_ok(i),
+                        Err(e) => inner.failure(i, &e),
+                    }
+                }
+            }
+        });
+    }
+}
+
+fn endpoint(config: &RpcEndpointConfig, timeout: Duration) -> Endpoint {
+    Endpoint {
+        label: config.label(),
+        weight: config.weight as i64,
+        sender: HttpSender::new_with_timeout(&config.url, timeout),
+        interval: config.max_rps.map(|rps| Duration::from_secs(1) / rps),
+        next_slot: tokio::sync::Mutex::new(Instant::now()),
+        circuit: Mutex::new(Circuit::default()),
+    }
+}
+
+// 节点本身的问题 (连不上、超时、5xx、限流、节点落后) 才切换节点；
+// 请求参数错误之类的 JSON-RPC 错误换个节点也一样，直接返回
+fn is_endpoint_failure(e: &ClientError) -> bool {
+    match e.kind() {
+        ClientErrorKind::Io(_) | ClientErrorKind::Reqwest(_) => true,
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. }) => *code == JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY,
+        _ => false,
+    }
+}
+
+#[async_trait]
+impl RpcSender for RpcPool {
+    async fn send(&self, request: RpcRequest, params: serde_json::Value) -> ClientResult<serde_json::Value> {
+        let inner = &self.inner;
+        let mut last_error = None;
+        for i in inner.order(Instant::now()) {
+            let endpoint = &inner.endpoints[i];
+            endpoint.throttle().await;
+            let Some(_probe) = inner.admit(i) else { continue };
+            let started = Instant::now();
+            let result = endpoint.sender.send(request, params.clone()).await;
+            let method = request.to_string();
+            let labels = [endpoint.label.as_str(), method.as_str()];
+            METRICS.rpc_latency.with_label_values(&labels).observe(started.elapsed().as_secs_f64());
+            if result.is_err() {
+                METRICS.rpc_errors.with_label_values(&labels).inc();
+            }
+            match result {
+                Ok(value) => {
+                    inner.success(i);
+                    return Ok(value);
+                }
+                Err(e) if is_endpoint_failure(&e) => {
+                    inner.failure(i, &e);
+                    last_error = Some(e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| ClientErrorKind::Custom("没有可用的 RPC 节点".to_string()).into()))
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        let mut total = RpcTransportStats::default();
+        for endpoint in &self.inner.endpoints {
+            let stats = endpoint.sender.get_transport_stats();
+            total.request_count += stats.request_count;
+            total.elapsed_time += stats.elapsed_time;
+            total.rate_limited_time += stats.rate_limited_time;
+        }
+        total
+    }
+
+    fn url(&self) -> String {
+        let labels: Vec<&str> = self.inner.endpoints.iter().map(|e| e.label.as_str()).collect();
+        format!("pool[{}]", labels.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RpcPoolConfig;
+    use crate::sinks::mock_server::MockServer;
+
+    fn config(urls: &[(&str, u32)], failure_threshold: u32) -> RpcConfig {
+        RpcConfig {
+            endpoints: urls
+                .iter()
+                .map(|(url, weight)| RpcEndpointConfig { name: None, url: url.to_string(), weight: *weight, max_rps: None })
+                .collect(),
+            pool: RpcPoolConfig { failure_threshold, cooldown_secs: 60, ..Default::default() },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn weighted_round_robin() {
+        let pool = RpcPool::new(&config(&[("http://a", 3), ("http://b", 1)], 3));
+        let now = Instant::now();
+        let picks: Vec<usize> = (0..8).map(|_| pool.inner.order(now)[0]).collect();
+        assert_eq!(picks, vec![0, 0, 1, 0, 0, 0, 1, 0]);
+    }
+
+    #[tokio::test]
+    async fn fails_over_and_opens_circuit() {
+        let flaky = MockServer::start(vec![(503, "Service Unavailable")]).await;
+        let healthy = MockServer::start(vec![
+            (200, r#"{"jsonrpc":"2.0","result":42,"id":0}"#),
+            (200, r#"{"jsonrpc":"2.0","result":43,"id":1}"#),
+        ])
+        .await;
+        let client = RpcPool::new(&config(&[(&flaky.url, 1), (&healthy.url, 1)], 1)).client();
+
+        // 第一次轮到 flaky，失败后切到 healthy
+        let slot = || client.send::<u64>(RpcRequest::GetSlot, serde_json::Value::Null);
+        assert_eq!(slot().await.unwrap(), 42);
+        // flaky 已经熔断，第二次直接走 healthy
+        assert_eq!(slot().await.unwrap(), 43);
+        assert_eq!(flaky.requests().len(), 1);
+        assert_eq!(healthy.requests().len(), 2);
+    }
+
+    #[test]
+    fn health_check_only_half_opens_circuit() {
+        let pool = RpcPool::new(&config(&[("http://a", 1)], 2));
+        let inner = &pool.inner;
+        let error = || ClientError::from(std::io::Error::other("connection refused"));
+        inner.failure(0, &error());
+        inner.failure(0, &error());
+        assert!(!inner.endpoints[0].available(Instant::now()));
+
+        // getHealth 通过后可以试探，但失败计数还在
+        inner.health_ok(0);
+        assert!(inner.endpoints[0].available(Instant::now()));
+        assert_eq!(pool.status()[0].consecutive_failures, 2);
+
+        // 试探的真实请求失败，立刻重新熔断
+        inner.failure(0, &error());
+        assert!(!inner.endpoints[0].available(Instant::now()));
+
+        // 试探成功才关闭
+        inner.health_ok(0);
+        inner.success(0);
+        assert_eq!(pool.status()[0].consecutive_failures, 0);
+        assert_eq!(inner.endpoints[0].circuit.lock().unwrap().state, CircuitState::Closed);
+    }
+
+    #[tokio::test]
+    async fn half_open_admits_a_single_probe() {
+        // 熔断过的节点：接受连接但一直不回，试探请求会挂到超时
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let tripped = format!("http://{}", listener.local_addr().unwrap());
+        let accepted = Arc::new(Mutex::new(Vec::new()));
+        tokio::spawn({
+            let accepted = accepted.clone();
+            async move {
+                while let Ok((socket, _)) = listener.accept().await {
+                    accepted.lock().unwrap().push(socket);
+                }
+            }
+        });
+        let result = r#"{"jsonrpc":"2.0","result":42,"id":0}"#;
+        let healthy = MockServer::start(vec![(200, result); 5]).await;
+
+        let mut config = config(&[(&tripped, 100), (&healthy.url, 1)], 1);
+        config.pool.timeout_secs = 1;
+        let pool = RpcPool::new(&config);
+        pool.inner.failure(0, &ClientError::from(std::io::Error::other("connection refused")));
+        pool.inner.health_ok(0);
+
+        // 5 个请求同时来，只有一个去试探，其余的直接走健康节点
+        let client = pool.client();
+        let slots = (0..5).map(|_| client.send::<u64>(RpcRequest::GetSlot, serde_json::Value::Null));
+        for slot in futures::future::join_all(slots).await {
+            assert_eq!(slot.unwrap(), 42);
+        }
+        assert_eq!(accepted.lock().unwrap().len(), 1);
+        // 试探超时，重新熔断
+        assert!(!pool.inner.endpoints[0].available(Instant::now()));
+    }
+}
@@ -0,0 +1,270 @@
+use super::AlertSink;
+use crate::balance::short_address;
+use crate::config::{DiscordSinkConfig, MessagesConfig};
+use crate::event::WhaleEvent;
+use crate::message::{Markup, Renderer};
+use anyhow::bail;
+use async_trait::async_trait;
+use serde_json::json;
+use solana_sdk::native_token::lamports_to_sol;
+use std::time::Duration;
+use tracing::warn;
+
+// 流向太多时只列出最大的几笔
+const MAX_FLOWS: usize = 5;
+// 被限流后最多重试几次，以及单次最长等待
+const MAX_ATTEMPTS: usize = 4;
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+// Solana 紫
+const EMBED_COLOR: u32 = 0x9945FF;
+// Discord 的长度限制，超过时整个 embed 会被 400 拒绝
+const MAX_FIELD_VALUE: usize = 1024;
+const MAX_DESCRIPTION: usize = 4096;
+
+// --- Discord webhook 报警通道：消息渲染成 embed ---
+pub struct DiscordSink {
+    client: reqwest::Client,
+    webhook_url: String,
+    username: Option<String>,
+    renderer: Renderer,
+}
+
+impl DiscordSink {
+    pub fn new(config: &DiscordSinkConfig, messages: &MessagesConfig) -> anyhow::Result<Self> {
+        Ok(Self {
+            client: reqwest::Client::builder().build()?,
+            webhook_url: config.webhook_url.clone(),
+            username: config.username.clone(),
+            renderer: Renderer::new(messages, &config.message, Markup::Markdown, None)?,
+        })
+    }
+}
+
+#[async_trait]
+impl AlertSink for DiscordSink {
+    async fn send(&self, event: &WhaleEvent) -> anyhow::Result<()> {
+        let mut body = json!({ "embeds": [render_embed(event, &self.renderer)?] });
+        if let Some(username) = &self.username {
+            body["username"] = json!(username);
+        }
+
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let res = self.client.post(&self.webhook_url).json(&body).send().await?;
+            let status = res.status();
+
+            if status.is_success() {
+                return Ok(());
+            }
+            // 429 以外的错误重试也没用
+            if status != reqwest::StatusCode::TOO_MANY_REQUESTS {
+                let text = res.text().await.unwrap_or_default();
+                bail!("Status {}: {}", status, text);
+            }
+            if attempt >= MAX_ATTEMPTS {
+                bail!("Discord 持续限流，放弃发送 (已重试 {} 次)", attempt);
+            }
+
+            // 429: 按 Discord 给的 retry_after 等一会儿再发
+            let wait = retry_after(res).await.min(MAX_RETRY_AFTER);
+            warn!(wait_secs = wait.as_secs_f64(), "⏳ Discord 限流，稍后重试");
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+// 优先读响应体里的 retry_after (秒，可以是小数)，其次是 Retry-After 头
+async fn retry_after(res: reqwest::Response) -> Duration {
+    let header = res
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<f64>().ok());
+    let body = res
+        .json::<serde_json::Value>()
+        .await
+        .ok()
+        .and_then(|v| v["retry_after"].as_f64());
+
+    Duration::from_secs_f64(body.or(header).unwrap_or(1.0).max(0.0))
+}
+
+// 有自定义模板时模板的输出作为 embed 的 description，否则按字段展示
+pub fn render_embed(event: &WhaleEvent, renderer: &Renderer) -> anyhow::Result<serde_json::Value> {
+    let t = renderer.strings();
+    let tx_url = renderer.explorer.tx_url(&event.signature);
+    // 没有出块时间 (processed 阶段) 时用当前时间
+    let timestamp = event
+        .block_time
+        .and_then(|t| chrono::DateTime::from_timestamp(t, 0))
+        .unwrap_or_else(chrono::Utc::now);
+    let mut embed = json!({
+        "title": format!("🐋 {}", t.title),
+        "url": tx_url,
+        "color": EMBED_COLOR,
+        "timestamp": timestamp.to_rfc3339(),
+    });
+
+    if let Some(description) = renderer.render(event)? {
+        embed["description"] = json!(truncate(&description, MAX_DESCRIPTION));
+        return Ok(embed);
+    }
+
+    let mut fields = Vec::new();
+
+    if let Some(sol) = &event.sol {
+        let flows: Vec<String> = sol
+            .transfers
+            .iter()
+            .take(MAX_FLOWS)
+            .map(|t| format!(
+                "`{}` → `{}`: {:.2} SOL",
+                short_address(&t.from), short_address(&t.to), lamports_to_sol(t.lamports)
+            ))
+            .collect();
+        push_field(&mut fields, format!("💰 {}", t.amount), format!("{:.2} SOL", sol.amount()), true);
+        push_field(&mut fields, format!("🔀 {}", t.flows), flows.join("\n"), false);
+    }
+
+    for token in &event.tokens {
+        let symbol = renderer.escape(renderer.symbol(&token.symbol));
+        let flows: Vec<String> = token
+            .transfers
+            .iter()
+            .take(MAX_FLOWS)
+            .map(|t| format!(
+                "`{}` → `{}`: {:.2} {}",
+                short_address(&t.from), short_address(&t.to), t.ui_amount(), symbol
+            ))
+            .collect();
+        push_field(&mut fields, format!("🪙 {}", symbol), format!("{:.2} {}\nMint: `{}`", token.amount, symbol, token.mint), true);
+        push_field(&mut fields, format!("🔀 {}", t.flows), flows.join("\n"), false);
+    }
+
+    if let Some(memo) = &event.memo {
+        push_field(&mut fields, format!("📝 {}", t.memo), renderer.escape(memo), false);
+    }
+    push_field(&mut fields, "📦 Slot".to_string(), event.slot.to_string(), true);
+    let fee = format!("{} SOL ({} `{}`)", lamports_to_sol(event.fee), t.payer, short_address(&event.fee_payer));
+    push_field(&mut fields, format!("⛽ {}", t.fee), fee, true);
+    let link = format!("[{}]({})", short_signature(&event.signature), tx_url);
+    push_field(&mut fields, format!("🔗 {}", t.transaction), link, false);
+    embed["fields"] = json!(fields);
+    Ok(embed)
+}
+
+// 空的 value 会让 Discord 拒绝整个 embed，这种字段直接不发
+fn push_field(fields: &mut Vec<serde_json::Value>, name: String, value: String, inline: bool) {
+    if value.trim().is_empty() {
+        return;
+    }
+    let mut field = json!({ "name": name, "value": truncate(&value, MAX_FIELD_VALUE) });
+    if inline {
+        field["inline"] = json!(true);
+    }
+    fields.push(field);
+}
+
+// 按字符截断 (Discord 按字符数算)，截掉时结尾换成省略号
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let mut out: String = text.chars().take(max - 1).collect();
+    out.push('…');
+    out
+}
+
+fn short_signature(signature: &str) -> String {
+    if signature.len() <= 16 {
+        return signature.to_string();
+    }
+    format!("{}…{}", &signature[..8], &signature[signature.len() - 8..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{MessageOptions, SinkFilter, ThresholdConfig};
+    use crate::fetch::FetchedTransaction;
+    use crate::sinks::mock_server::MockServer;
+
+    fn legacy_event() -> WhaleEvent {
+        let json = include_str!("../../tests/fixtures/legacy_transfer.json");
+        let tx = FetchedTransaction::parse(serde_json::from_str(json).unwrap()).unwrap();
+        WhaleEvent::detect(&tx, &ThresholdConfig::default()).unwrap()
+    }
+
+    fn sink(url: &str) -> DiscordSink {
+        DiscordSink::new(&DiscordSinkConfig {
+            name: None,
+            webhook_url: format!("{}/api/webhooks/1/token", url),
+            username: Some("Whale Watcher".to_string()),
+            filter: SinkFilter::default(),
+            message: MessageOptions::default(),
+        }, &MessagesConfig::default())
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn posts_embed() {
+        let server = MockServer::start(vec![(204, "")]).await;
+        sink(&server.url).send(&legacy_event()).await.unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].path, "/api/webhooks/1/token");
+        assert_eq!(requests[0].header("content-type"), Some("application/json"));
+
+        let body = requests[0].json();
+        assert_eq!(body["username"], "Whale Watcher");
+        let embed = &body["embeds"][0];
+        assert_eq!(
+            embed["url"],
+            "https://solscan.io/tx/476Quh27MKjtmgciYgbvDcYNPkXR9TEAWP2GVkLQEEHQvLR2bMXbA7xAyY8hs2c4mKHTM4m1X2NRDdJGRxKzGJ8i"
+        );
+        assert_eq!(embed["timestamp"], "2024-09-22T10:13:20+00:00");
+        assert_eq!(embed["fields"][0]["value"], "250.00 SOL");
+        assert_eq!(embed["fields"][1]["value"], "`4Ypn…Jsx2` → `DTKn…5Tzz`: 250.00 SOL");
+    }
+
+    #[tokio::test]
+    async fn retries_after_rate_limit() {
+        let server = MockServer::start(vec![
+            (429, r#"{"message": "You are being rate limited.", "retry_after": 0.05, "global": false}"#),
+            (204, ""),
+        ])
+        .await;
+        sink(&server.url).send(&legacy_event()).await.unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].body, requests[1].body);
+    }
+
+    #[test]
+    fn skips_empty_fields_and_truncates() {
+        let renderer = Renderer::new(&MessagesConfig::default(), &MessageOptions::default(), Markup::Markdown, None).unwrap();
+        let mut event = legacy_event();
+        event.sol.as_mut().unwrap().transfers.clear();
+        event.memo = Some("鲸".repeat(2000));
+
+        let embed = render_embed(&event, &renderer).unwrap();
+        let fields = embed["fields"].as_array().unwrap();
+        assert!(fields.iter().all(|f| !f["value"].as_str().unwrap().is_empty()));
+        let memo = fields.iter().find(|f| f["name"].as_str().unwrap().starts_with("📝")).unwrap();
+        let memo = memo["value"].as_str().unwrap();
+        assert_eq!(memo.chars().count(), MAX_FIELD_VALUE);
+        assert!(memo.ends_with('…'));
+    }
+
+    #[tokio::test]
+    async fn fails_on_client_error() {
+        let server = MockServer::start(vec![(400, r#"{"message": "Invalid Form Body"}"#)]).await;
+        let err = sink(&server.url).send(&legacy_event()).await.unwrap_err();
+
+        assert!(err.to_string().contains("Invalid Form Body"), "{}", err);
+        assert_eq!(server.requests().len(), 1);
+    }
+}
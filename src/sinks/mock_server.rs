@@ -0,0 +1,103 @@
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+// 测试用的极简 HTTP 服务器：按顺序返回预设的响应，并记录收到的请求
+pub struct MockServer {
+    pub url: String,
+    requests: Arc<Mutex<Vec<MockRequest>>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct MockRequest {
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl MockRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::from_str(&self.body).unwrap()
+    }
+}
+
+impl MockServer {
+    // responses: (状态码, 响应体)，每个连接消耗一个
+    pub async fn start(responses: Vec<(u16, &str)>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let responses: Vec<(u16, String)> = responses.into_iter().map(|(s, b)| (s, b.to_string())).collect();
+
+        let recorded = requests.clone();
+        tokio::spawn(async move {
+            for (status, body) in responses {
+                let Ok((mut socket, _)) = listener.accept().await else { return };
+                let request = read_request(&mut socket).await;
+                recorded.lock().unwrap().push(request);
+
+                let response = format!(
+                    "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+                let _ = socket.shutdown().await;
+            }
+        });
+
+        Self { url, requests }
+    }
+
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+async fn read_request(socket: &mut tokio::net::TcpStream) -> MockRequest {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let header_end = loop {
+        let n = socket.read(&mut chunk).await.unwrap();
+        buf.extend_from_slice(&chunk[..n]);
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        if n == 0 {
+            break buf.len();
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+    let mut lines = head.split("\r\n");
+    let path = lines.next().unwrap_or_default().split(' ').nth(1).unwrap_or_default().to_string();
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|l| l.split_once(':'))
+        .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+        .collect();
+
+    let length: usize = headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, v)| v.parse().ok())
+        .unwrap_or(0);
+    while buf.len() < header_end + length {
+        let n = socket.read(&mut chunk).await.unwrap();
+        if n == 0 { break; }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+
+    MockRequest {
+        path,
+        headers,
+        body: String::from_utf8_lossy(&buf[header_end..]).to_string(),
+    }
+}
@@ -0,0 +1,305 @@
+pub mod discord;
+pub mod slack;
+pub mod telegram;
+pub mod webhook;
+
+#[cfg(test)]
+pub mod mock_server;
+
+use crate::config::{Config, MessageOptions, SinkConfig, SinkFilter};
+use crate::event::{exceeds, Severity, WhaleEvent};
+use crate::message::Renderer;
+use crate::metrics::METRICS;
+use async_trait::async_trait;
+use discord::DiscordSink;
+use futures::future::join_all;
+use serde::Serialize;
+use std::sync::Mutex;
+use tracing::{info, warn};
+use slack::SlackSink;
+use telegram::{TelegramOutbox, TelegramSink};
+use webhook::WebhookSink;
+
+// 报警通道：拿到结构化的巨鲸事件，自己决定怎么渲染、发到哪里
+#[async_trait]
+pub trait AlertSink: Send + Sync {
+    async fn send(&self, event: &WhaleEvent) -> anyhow::Result<()>;
+
+    // 交易状态变了 (最终确认 / 被丢弃)，能修改已发消息的通道在这里更新，默认什么都不做
+    async fn update(&self, _event: &WhaleEvent) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    // 实现了 update 的通道返回 true；没有这样的通道发出报警时就不用等最终确认
+    fn supports_update(&self) -> bool {
+        false
+    }
+}
+
+impl SinkFilter {
+    pub fn matches(&self, event: &WhaleEvent) -> bool {
+        self.matches_at(event, chrono::Utc::now())
+    }
+
+    pub fn matches_at(&self, event: &WhaleEvent, now: chrono::DateTime<chrono::Utc>) -> bool {
+        if let Some(quiet) = &self.quiet_hours
+            && quiet.contains(now)
+            && !(quiet.allow_critical && event.severity == Severity::Critical)
+        {
+            return false;
+        }
+        if !self.wallets.is_empty() && !self.wallets.iter().any(|w| event.involves(w)) {
+            return false;
+        }
+        if !self.feeds.is_empty() && !self.feeds.contains(&event.feed) {
+            return false;
+        }
+
+        let sol = event.sol.is_some() && (self.min_sol == 0.0 || exceeds(event.sol_amount(), self.min_sol));
+        let tokens = self.tokens
+            && event.tokens.iter().any(|t| self.mints.is_empty() || self.mints.contains(&t.mint));
+        sol || tokens
+    }
+}
+
+struct Sink {
+    name: String,
+    filter: SinkFilter,
+    inner: Box<dyn AlertSink>,
+    // 最近的发送结果，成功一次清零
+    health: Mutex<SinkStatus>,
+}
+
+impl Sink {
+    fn new(name: String, filter: SinkFilter, inner: Box<dyn AlertSink>) -> Self {
+        let health = Mutex::new(SinkStatus { name: name.clone(), ..Default::default() });
+        Self { name, filter, inner, health }
+    }
+
+    fn record(&self, result: &anyhow::Result<()>) {
+        let now = chrono::Utc::now().timestamp();
+        let mut health = self.health.lock().unwrap();
+        match result {
+            Ok(()) => {
+                health.consecutive_failures = 0;
+                health.last_success_at = Some(now);
+            }
+            Err(_) => {
+                health.consecutive_failures += 1;
+                health.last_failure_at = Some(now);
+            }
+        }
+    }
+}
+
+// /healthz 和 /readyz 里一个报警通道的状态
+// 不带错误内容：webhook 地址里有 token，错误信息经常带着 URL，具体原因看日志
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SinkStatus {
+    pub name: String,
+    pub consecutive_failures: u64,
+    // unix 时间戳
+    pub last_success_at: Option<i64>,
+    pub last_failure_at: Option<i64>,
+}
+
+// 把一个事件同时分发给所有匹配的通道，每个通道的失败互不影响
+pub struct Dispatcher {
+    sinks: Vec<Sink>,
+    // 终端里打印的预览，和 Telegram 默认格式一样
+    console: Renderer,
+    // bot 回复命令也走这个队列，和报警共用频率限制
+    telegram: Option<TelegramOutbox>,
+}
+
+impl Dispatcher {
+    pub fn from_config(config: &Config) -> anyhow::Result<Self> {
+        let mut sinks = Vec::new();
+        // 所有 Telegram 通道共用一个发送队列，validate 已经保证用到时一定有 token
+        let outbox = match &config.telegram.token {
+            Some(token) => Some(TelegramOutbox::spawn(&config.telegram, token)?),
+            None => None,
+        };
+
+        // 兼容旧配置：[telegram] 里直接写 chat_id 时当作不过滤的通道，多个用逗号分隔
+        if let (Some(outbox), Some(chat_ids)) = (&outbox, &config.telegram.chat_id) {
+            let chat_ids: Vec<&str> = chat_ids.split(',').map(str::trim).filter(|id| !id.is_empty()).collect();
+            for chat_id in &chat_ids {
+                let name = if chat_ids.len() == 1 { "telegram".to_string() } else { format!("telegram:{}", chat_id) };
+                let inner = TelegramSink::new(
+                    outbox.clone(),
+                    chat_id,
+                    telegram::renderer(&config.messages, &MessageOptions::default())?,
+                );
+                sinks.push(Sink::new(name, SinkFilter::default(), Box::new(inner)));
+            }
+        }
+
+        for (i, sink) in config.sinks.iter().enumerate() {
+            let inner: Box<dyn AlertSink> = match sink {
+                SinkConfig::Telegram(t) => {
+                    let outbox = outbox.clone().expect("validate 已经保证有 telegram.token");
+                    Box::new(TelegramSink::new(outbox, &t.chat_id, telegram::renderer(&config.messages, &t.message)?))
+                }
+                SinkConfig::Discord(d) => Box::new(DiscordSink::new(d, &config.messages)?),
+                SinkConfig::Slack(s) => Box::new(SlackSink::new(s, &config.messages)?),
+                SinkConfig::Webhook(w) => Box::new(WebhookSink::new(w)?),
+            };
+            let name = sink.name().map_or_else(|| format!("{}#{}", sink.kind(), i), str::to_string);
+            sinks.push(Sink::new(name, sink.filter().clone(), inner));
+        }
+
+        let console = telegram::renderer(&config.messages, &MessageOptions::default())?;
+        Ok(Self { sinks, console, telegram: outbox })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sinks.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.sinks.len()
+    }
+
+    pub fn preview(&self, event: &WhaleEvent) -> String {
+        match self.console.render(event) {
+            Ok(text) => text.unwrap_or_default(),
+            Err(e) => format!("⚠️ 渲染失败: {:#}", e),
+        }
+    }
+
+    pub fn status(&self) -> Vec<SinkStatus> {
+        self.sinks.iter().map(|s| s.health.lock().unwrap().clone()).collect()
+    }
+
+    pub fn telegram(&self) -> Option<&TelegramOutbox> {
+        self.telegram.as_ref()
+    }
+
+    // 返回是否有支持 update 的通道发送成功，调用方据此决定要不要等最终状态
+    pub async fn dispatch(&self, event: &WhaleEvent) -> bool {
+        let sends = self.sinks.iter().filter(|s| s.filter.matches(event)).map(|sink| async move {
+            let result = sink.inner.send(event).await;
+            sink.record(&result);
+            match result {
+                Ok(()) => {
+                    info!(sink = %sink.name, "✅ 报警发送成功!");
+                    METRICS.sink_sent.with_label_values(&[&sink.name]).inc();
+                    sink.inner.supports_update()
+                }
+                Err(e) => {
+                    warn!(sink = %sink.name, error = format!("{:#}", e), "⚠️ 报警发送失败");
+                    METRICS.sink_failures.with_label_values(&[&sink.name, "send"]).inc();
+                    false
+                }
+            }
+        });
+        join_all(sends).await.into_iter().any(|updatable| updatable)
+    }
+
+    // 没有发送过这个事件的通道自己会忽略，所以这里不再按过滤条件筛选
+    pub async fn update(&self, event: &WhaleEvent) {
+        let updates = self.sinks.iter().map(|sink| async move {
+            if let Err(e) = sink.inner.update(event).await {
+                warn!(sink = %sink.name, error = format!("{:#}", e), "⚠️ 报警状态更新失败");
+                METRICS.sink_failures.with_label_values(&[&sink.name, "update"]).inc();
+            }
+        });
+        join_all(updates).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{QuietHours, ThresholdConfig};
+    use crate::fetch::FetchedTransaction;
+    use chrono::{TimeZone, Utc};
+    use solana_sdk::pubkey::Pubkey;
+    use std::str::FromStr;
+
+    fn legacy_event() -> WhaleEvent {
+        let json = include_str!("../../tests/fixtures/legacy_transfer.json");
+        let tx = FetchedTransaction::parse(serde_json::from_str(json).unwrap()).unwrap();
+        WhaleEvent::detect(&tx, &ThresholdConfig::default()).unwrap()
+    }
+
+    #[test]
+    fn filter_by_min_sol() {
+        let event = legacy_event();
+        assert_eq!(event.sol_amount(), 250.0);
+
+        assert!(SinkFilter::default().matches(&event));
+        assert!(SinkFilter { min_sol: 100.0, tokens: false, ..Default::default() }.matches(&event));
+        // 只收 >1000 SOL 的通道不应该收到 250 SOL 的事件
+        assert!(!SinkFilter { min_sol: 1000.0, tokens: false, ..Default::default() }.matches(&event));
+        // 正好 250 SOL 不算超过
+        assert!(!SinkFilter { min_sol: 250.0, tokens: false, ..Default::default() }.matches(&event));
+    }
+
+    #[test]
+    fn filter_by_wallets_feeds_and_quiet_hours() {
+        let event = legacy_event();
+        let receiver = Pubkey::from_str("DTKntNWAAidsxiD8QkMscLbGJxTEayn6ecLaVt635Tzz").unwrap();
+        assert!(SinkFilter { wallets: vec![receiver], ..Default::default() }.matches(&event));
+        assert!(!SinkFilter { wallets: vec![Pubkey::new_unique()], ..Default::default() }.matches(&event));
+
+        let mut tagged = event.clone();
+        tagged.feed = "token-2022".to_string();
+        let filter = SinkFilter { feeds: vec!["token-2022".to_string()], ..Default::default() };
+        assert!(filter.matches(&tagged));
+        assert!(!filter.matches(&event));
+
+        // 北京时间 23:00 - 07:00 免打扰
+        let quiet = QuietHours {
+            start: "23:00".to_string(),
+            end: "07:00".to_string(),
+            utc_offset: "+08:00".to_string(),
+            allow_critical: false,
+        };
+        let filter = SinkFilter { quiet_hours: Some(quiet.clone()), ..Default::default() };
+        let night = Utc.with_ymd_and_hms(2024, 9, 22, 18, 30, 0).unwrap(); // 北京时间 02:30
+        let day = Utc.with_ymd_and_hms(2024, 9, 22, 4, 0, 0).unwrap(); // 北京时间 12:00
+        assert!(!filter.matches_at(&event, night));
+        assert!(filter.matches_at(&event, day));
+
+        // 250 SOL 是 critical，允许的话免打扰时段也照发
+        let filter = SinkFilter { quiet_hours: Some(QuietHours { allow_critical: true, ..quiet }), ..Default::default() };
+        assert!(filter.matches_at(&event, night));
+    }
+
+    struct FakeSink {
+        updatable: bool,
+    }
+
+    #[async_trait]
+    impl AlertSink for FakeSink {
+        async fn send(&self, _event: &WhaleEvent) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn supports_update(&self) -> bool {
+            self.updatable
+        }
+    }
+
+    #[tokio::test]
+    async fn dispatch_reports_whether_an_updatable_sink_sent() {
+        let dispatcher = |sinks: Vec<Sink>| Dispatcher {
+            sinks,
+            console: telegram::renderer(&Default::default(), &MessageOptions::default()).unwrap(),
+            telegram: None,
+        };
+        let sink = |updatable, min_sol| {
+            let filter = SinkFilter { min_sol, tokens: false, ..Default::default() };
+            Sink::new("fake".to_string(), filter, Box::new(FakeSink { updatable }))
+        };
+        let event = legacy_event();
+
+        // 只有 Discord / Slack / webhook 这类通道时不用等最终确认
+        assert!(!dispatcher(vec![sink(false, 0.0)]).dispatch(&event).await);
+        assert!(dispatcher(vec![sink(false, 0.0), sink(true, 0.0)]).dispatch(&event).await);
+        // 能更新的通道被过滤掉了，同样不用等
+        assert!(!dispatcher(vec![sink(false, 0.0), sink(true, 1000.0)]).dispatch(&event).await);
+    }
+}
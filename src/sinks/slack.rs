@@ -0,0 +1,226 @@
+use super::AlertSink;
+use crate::balance::short_address;
+use crate::config::{MessagesConfig, SlackSinkConfig};
+use crate::event::{Severity, WhaleEvent};
+use crate::message::{Markup, Renderer};
+use anyhow::bail;
+use async_trait::async_trait;
+use serde_json::json;
+use solana_sdk::native_token::lamports_to_sol;
+
+// 流向太多时只列出最大的几笔
+const MAX_FLOWS: usize = 5;
+
+// --- Slack incoming webhook 报警通道：消息渲染成 Block Kit ---
+pub struct SlackSink {
+    client: reqwest::Client,
+    config: SlackSinkConfig,
+    renderer: Renderer,
+}
+
+impl SlackSink {
+    pub fn new(config: &SlackSinkConfig, messages: &MessagesConfig) -> anyhow::Result<Self> {
+        Ok(Self {
+            client: reqwest::Client::builder().build()?,
+            config: config.clone(),
+            renderer: Renderer::new(messages, &config.message, Markup::Mrkdwn, None)?,
+        })
+    }
+}
+
+#[async_trait]
+impl AlertSink for SlackSink {
+    async fn send(&self, event: &WhaleEvent) -> anyhow::Result<()> {
+        let url = self.config.webhook_for(event.severity);
+        let res = self.client.post(url).json(&render_blocks(event, &self.renderer)?).send().await?;
+
+        // 成功时 Slack 返回 200 和纯文本 "ok"，失败时返回错误码 (如 invalid_blocks)
+        if !res.status().is_success() {
+            let status = res.status();
+            let text = res.text().await.unwrap_or_default();
+            bail!("Status {}: {}", status, text);
+        }
+        Ok(())
+    }
+}
+
+// 有自定义模板时模板的输出作为一个 mrkdwn section 替换掉金额和流向部分，标题和按钮保留
+pub fn render_blocks(event: &WhaleEvent, renderer: &Renderer) -> anyhow::Result<serde_json::Value> {
+    let t = renderer.strings();
+    let tx_url = renderer.explorer.tx_url(&event.signature);
+    let mut blocks = vec![json!({
+        "type": "header",
+        "text": { "type": "plain_text", "text": format!("🐋 {} [{}]", t.title, event.severity.label()) },
+    })];
+    // 通知栏和不支持 blocks 的客户端显示这个
+    let mut summary = Vec::new();
+    if let Some(sol) = &event.sol {
+        summary.push(format!("{:.2} SOL", sol.amount()));
+    }
+    for token in &event.tokens {
+        summary.push(format!("{:.2} {}", token.amount, renderer.escape(renderer.symbol(&token.symbol))));
+    }
+
+    match renderer.render(event)? {
+        Some(text) => blocks.push(json!({ "type": "section", "text": { "type": "mrkdwn", "text": text } })),
+        None => blocks.extend(default_sections(event, renderer)),
+    }
+
+    let mut button = json!({
+        "type": "button",
+        "text": { "type": "plain_text", "text": format!("🔗 {}", t.view_tx) },
+        "url": tx_url,
+    });
+    if event.severity == Severity::Critical {
+        button["style"] = json!("danger");
+    }
+    blocks.push(json!({ "type": "actions", "elements": [button] }));
+
+    Ok(json!({
+        "text": format!("🐋 {} {}", t.title, summary.join(", ")),
+        "blocks": blocks,
+    }))
+}
+
+fn default_sections(event: &WhaleEvent, renderer: &Renderer) -> Vec<serde_json::Value> {
+    let t = renderer.strings();
+    let mut blocks = Vec::new();
+
+    if let Some(sol) = &event.sol {
+        let flows: Vec<String> = sol
+            .transfers
+            .iter()
+            .take(MAX_FLOWS)
+            .map(|t| format!(
+                "• `{}` → `{}`: {:.2} SOL",
+                short_address(&t.from), short_address(&t.to), lamports_to_sol(t.lamports)
+            ))
+            .collect();
+        blocks.push(json!({
+            "type": "section",
+            "text": {
+                "type": "mrkdwn",
+                "text": format!("*💰 {}:* {:.2} SOL\n*🔀 {}:*\n{}", t.amount, sol.amount(), t.flows, flows.join("\n")),
+            },
+        }));
+    }
+
+    for token in &event.tokens {
+        let symbol = renderer.escape(renderer.symbol(&token.symbol));
+        let flows: Vec<String> = token
+            .transfers
+            .iter()
+            .take(MAX_FLOWS)
+            .map(|t| format!(
+                "• `{}` → `{}`: {:.2} {}",
+                short_address(&t.from), short_address(&t.to), t.ui_amount(), symbol
+            ))
+            .collect();
+        blocks.push(json!({
+            "type": "section",
+            "fields": [
+                { "type": "mrkdwn", "text": format!("*🪙 {}:*\n{:.2}", symbol, token.amount) },
+                { "type": "mrkdwn", "text": format!("*🏷 Mint:*\n`{}`", token.mint) },
+            ],
+        }));
+        blocks.push(json!({
+            "type": "section",
+            "text": { "type": "mrkdwn", "text": format!("*🔀 {}:*\n{}", t.flows, flows.join("\n")) },
+        }));
+    }
+
+    if let Some(memo) = &event.memo {
+        blocks.push(json!({
+            "type": "section",
+            "text": { "type": "mrkdwn", "text": format!("*📝 {}:* {}", t.memo, renderer.escape(memo)) },
+        }));
+    }
+
+    blocks.push(json!({
+        "type": "context",
+        "elements": [{
+            "type": "mrkdwn",
+            "text": format!(
+                "⛽ {}: {} SOL ({} `{}`) · 📦 Slot {}",
+                t.fee, lamports_to_sol(event.fee), t.payer, short_address(&event.fee_payer), event.slot
+            ),
+        }],
+    }));
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{MessageOptions, SinkFilter, SlackRoute, ThresholdConfig};
+    use crate::message::{Explorer, Locale};
+    use crate::fetch::FetchedTransaction;
+    use crate::sinks::mock_server::MockServer;
+
+    fn event(fixture: &str) -> WhaleEvent {
+        let tx = FetchedTransaction::parse(serde_json::from_str(fixture).unwrap()).unwrap();
+        WhaleEvent::detect(&tx, &ThresholdConfig { sol: 10.0, ..Default::default() }).unwrap()
+    }
+
+    #[tokio::test]
+    async fn routes_by_severity() {
+        let server = MockServer::start(vec![(200, "ok"), (200, "ok")]).await;
+        let sink = SlackSink::new(&SlackSinkConfig {
+            name: None,
+            webhook_url: format!("{}/services/default", server.url),
+            routes: vec![SlackRoute {
+                severity: Severity::Critical,
+                webhook_url: format!("{}/services/oncall", server.url),
+            }],
+            filter: SinkFilter::default(),
+            message: MessageOptions::default(),
+        }, &MessagesConfig::default())
+        .unwrap();
+
+        // 250 SOL / 阈值 10 = 25 倍，1200 SOL = 120 倍
+        let warning = event(include_str!("../../tests/fixtures/legacy_transfer.json"));
+        let critical = event(include_str!("../../tests/fixtures/v0_lookup_transfer.json"));
+        assert_eq!(warning.severity, Severity::Warning);
+        assert_eq!(critical.severity, Severity::Critical);
+
+        sink.send(&warning).await.unwrap();
+        sink.send(&critical).await.unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests[0].path, "/services/default");
+        assert_eq!(requests[1].path, "/services/oncall");
+
+        let body = requests[1].json();
+        assert_eq!(body["text"], "🐋 巨鲸警报! 1200.00 SOL");
+        let blocks = body["blocks"].as_array().unwrap();
+        assert_eq!(blocks[0]["text"]["text"], "🐋 巨鲸警报! [CRITICAL]");
+        let button = &blocks.last().unwrap()["elements"][0];
+        assert_eq!(button["style"], "danger");
+        assert_eq!(
+            button["url"],
+            "https://solscan.io/tx/Y8Cno71bKmkzm2KGD9LTjbo2WXjfgsoGvajqhooYTuKADn4iUSqvAeQ9brtRRmRxQC5DeKamoSmSxb5mxZaL39j"
+        );
+    }
+
+    #[test]
+    fn custom_template_and_locale() {
+        let mut warning = event(include_str!("../../tests/fixtures/legacy_transfer.json"));
+        warning.memo = Some("<!channel> gm".to_string());
+        let options = MessageOptions {
+            template: Some("*{{ sol.amount }} SOL* from `{{ fee_payer_short }}`: {{ memo }}".to_string()),
+            locale: Some(Locale::En),
+            explorer: Some(Explorer::Solana),
+            ..Default::default()
+        };
+        let renderer = Renderer::new(&MessagesConfig::default(), &options, Markup::Mrkdwn, None).unwrap();
+
+        let body = render_blocks(&warning, &renderer).unwrap();
+        let blocks = body["blocks"].as_array().unwrap();
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(blocks[0]["text"]["text"], "🐋 Whale Alert! [WARNING]");
+        // 备注里的 <!channel> 不能真的 @ 所有人
+        assert_eq!(blocks[1]["text"]["text"], "*250.00 SOL* from `4Ypn…Jsx2`: &lt;!channel&gt; gm");
+        assert_eq!(blocks[2]["elements"][0]["text"]["text"], "🔗 View transaction");
+        assert!(blocks[2]["elements"][0]["url"].as_str().unwrap().starts_with("https://explorer.solana.com/tx/"));
+    }
+}
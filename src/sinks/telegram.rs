@@ -0,0 +1,383 @@
+use super::AlertSink;
+use crate::backoff::Backoff;
+use crate::config::{MessageOptions, MessagesConfig, ProxyConfig, ProxyMode, TelegramConfig, TelegramRateLimit};
+use crate::event::{TxStatus, WhaleEvent};
+use crate::message::{Markup, Renderer};
+use anyhow::anyhow;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::Instant;
+use tracing::warn;
+
+// 自定义模板可以从这个文件改起
+const DEFAULT_TEMPLATE: &str = include_str!("../../templates/telegram.html");
+// 等待最终状态的报警最多记这么多条、这么久，处理任务中途退出时不会一直占着内存
+const MAX_PENDING: usize = 1000;
+const PENDING_TTL: Duration = Duration::from_secs(600);
+// 单次 429 最多等这么久；429 和其他错误一起计入 max_retries，一直被限流的聊天不会卡住整个队列
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+// --- Telegram 报警通道：只负责渲染，真正的发送交给共用的 TelegramOutbox ---
+pub struct TelegramSink {
+    outbox: TelegramOutbox,
+    chat_id: String,
+    renderer: Renderer,
+    // 还没到最终状态的报警：签名 -> (message_id, 发送时间)，状态更新后编辑这条消息
+    pending: Mutex<HashMap<String, (i64, Instant)>>,
+}
+
+impl TelegramSink {
+    pub fn new(outbox: TelegramOutbox, chat_id: &str, renderer: Renderer) -> Self {
+        Self { outbox, chat_id: chat_id.to_string(), renderer, pending: Mutex::new(HashMap::new()) }
+    }
+
+    // 先清掉过期的，还是满了就挤掉最早的一条
+    fn remember(&self, signature: &str, message_id: i64) {
+        let now = Instant::now();
+        let mut pending = self.pending.lock().unwrap();
+        pending.retain(|_, (_, sent)| now.duration_since(*sent) < PENDING_TTL);
+        if pending.len() >= MAX_PENDING
+            && let Some(oldest) = pending.iter().min_by_key(|(_, (_, sent))| *sent).map(|(k, _)| k.clone())
+        {
+            pending.remove(&oldest);
+        }
+        pending.insert(signature.to_string(), (message_id, now));
+    }
+
+    fn render(&self, event: &WhaleEvent) -> anyhow::Result<String> {
+        // Telegram 的 renderer 一定带着默认模板
+        Ok(self.renderer.render(event)?.unwrap_or_default())
+    }
+}
+
+// Telegram 消息用 HTML 格式，没有自定义模板时用 templates/telegram.html
+pub fn renderer(messages: &MessagesConfig, options: &MessageOptions) -> anyhow::Result<Renderer> {
+    Renderer::new(messages, options, Markup::Html, Some(DEFAULT_TEMPLATE))
+}
+
+#[async_trait]
+impl AlertSink for TelegramSink {
+    async fn send(&self, event: &WhaleEvent) -> anyhow::Result<()> {
+        let message_id = self.outbox.send(&self.chat_id, self.render(event)?).await?;
+        if event.status == TxStatus::Confirmed {
+            self.remember(&event.signature, message_id);
+        }
+        Ok(())
+    }
+
+    async fn update(&self, event: &WhaleEvent) -> anyhow::Result<()> {
+        let Some((message_id, _)) = self.pending.lock().unwrap().remove(&event.signature) else { return Ok(()) };
+        self.outbox.edit(&self.chat_id, message_id, self.render(event)?).await
+    }
+
+    fn supports_update(&self) -> bool {
+        true
+    }
+}
+
+// 所有 Telegram 通道共用一个发送队列：单个后台任务按入队顺序逐条发送，
+// 这样全局和每个聊天的频率限制才能统一控制，报警顺序也不会乱
+#[derive(Clone)]
+pub struct TelegramOutbox {
+    tx: mpsc::UnboundedSender<Outgoing>,
+}
+
+struct Outgoing {
+    // 频率限制按聊天计算
+    chat_id: String,
+    method: &'static str,
+    params: serde_json::Value,
+    done: oneshot::Sender<anyhow::Result<serde_json::Value>>,
+}
+
+impl TelegramOutbox {
+    // reqwest Client 只在这里建一次，之后每条报警复用
+    pub fn spawn(config: &TelegramConfig, token: &str) -> anyhow::Result<Self> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let worker = OutboxWorker {
+            client: build_client(&config.proxy)?,
+            base_url: format!("{}/bot{}", config.api_url.trim_end_matches('/'), token),
+            limits: config.rate_limit.clone(),
+            last_sent: None,
+            last_sent_per_chat: HashMap::new(),
+        };
+        tokio::spawn(worker.run(rx));
+        Ok(Self { tx })
+    }
+
+    // 排队发送，等到这条消息真正发出去 (或彻底失败) 才返回，成功时返回 message_id
+    pub async fn send(&self, chat_id: &str, text: String) -> anyhow::Result<i64> {
+        let params = serde_json::json!({
+            "chat_id": chat_id, // 这里的 chat_id 如果包含空格或换行符会导致 400
+            "text": text,
+            "parse_mode": "HTML",
+            "disable_web_page_preview": true
+        });
+        let message = self.call(chat_id, "sendMessage", params).await?;
+        message["message_id"].as_i64().ok_or_else(|| anyhow!("sendMessage 的响应里没有 message_id: {}", message))
+    }
+
+    // 编辑已经发出去的消息，和发送共用同一个队列和频率限制
+    pub async fn edit(&self, chat_id: &str, message_id: i64, text: String) -> anyhow::Result<()> {
+        let params = serde_json::json!({
+            "chat_id": chat_id,
+            "message_id": message_id,
+            "text": text,
+            "parse_mode": "HTML",
+            "disable_web_page_preview": true
+        });
+        match self.call(chat_id, "editMessageText", params).await {
+            // 内容没变时 Telegram 会返回 400，不算失败
+            Err(e) if e.to_string().contains("message is not modified") => Ok(()),
+            result => result.map(drop),
+        }
+    }
+
+    async fn call(&self, chat_id: &str, method: &'static str, params: serde_json::Value) -> anyhow::Result<serde_json::Value> {
+        let (done, result) = oneshot::channel();
+        let outgoing = Outgoing { chat_id: chat_id.to_string(), method, params, done };
+        self.tx.send(outgoing).map_err(|_| anyhow!("Telegram 发送队列已关闭"))?;
+        result.await.map_err(|_| anyhow!("Telegram 发送队列已关闭"))?
+    }
+}
+
+enum Attempt {
+    // 成功时带上响应里的 result
+    Sent(serde_json::Value),
+    // 429：Telegram 在 parameters.retry_after 里告诉我们要等多久
+    RetryAfter(Duration),
+    // 5xx / 网络错误，退避后重试
+    Transient(anyhow::Error),
+    // 其余 4xx (chat 不存在、HTML 格式错误...)，重试也没用
+    Fatal(anyhow::Error),
+}
+
+struct OutboxWorker {
+    client: reqwest::Client,
+    base_url: String,
+    limits: TelegramRateLimit,
+    last_sent: Option<Instant>,
+    last_sent_per_chat: HashMap<String, Instant>,
+}
+
+impl OutboxWorker {
+    async fn run(mut self, mut rx: mpsc::UnboundedReceiver<Outgoing>) {
+        while let Some(outgoing) = rx.recv().await {
+            let result = self.deliver(&outgoing).await;
+            // 调用方可能已经不等了，忽略即可
+            let _ = outgoing.done.send(result);
+        }
+    }
+
+    async fn deliver(&mut self, outgoing: &Outgoing) -> anyhow::Result<serde_json::Value> {
+        let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(30));
+        let mut retries = 0;
+
+        loop {
+            self.wait_turn(&outgoing.chat_id).await;
+            match self.attempt(outgoing.method, &outgoing.params).await {
+                Attempt::Sent(result) => return Ok(result),
+                Attempt::RetryAfter(wait) => {
+                    if retries >= self.limits.max_retries {
+                        return Err(anyhow!("Telegram 持续限流，重试 {} 次后放弃", retries));
+                    }
+                    retries += 1;
+                    let wait = wait.min(MAX_RETRY_AFTER);
+                    warn!(attempt = retries, wait_secs = wait.as_secs(), "⏳ Telegram 限流，稍后重试");
+                    tokio::time::sleep(wait).await;
+                }
+                Attempt::Transient(e) => {
+                    if retries >= self.limits.max_retries {
+                        return Err(e.context(format!("重试 {} 次后仍然失败", retries)));
+                    }
+                    retries += 1;
+                    warn!(attempt = retries, error = format!("{:#}", e), "⚠️ Telegram 发送失败，稍后重试");
+                    tokio::time::sleep(backoff.next_delay()).await;
+                }
+                Attempt::Fatal(e) => return Err(e),
+            }
+        }
+    }
+
+    // 同时满足全局间隔和该聊天的间隔后才轮到发送
+    async fn wait_turn(&mut self, chat_id: &str) {
+        let global_interval = Duration::from_secs(1) / self.limits.global_per_second;
+        let chat_interval = Duration::from_millis(self.limits.per_chat_interval_ms);
+
+        let mut ready = Instant::now();
+        if let Some(last) = self.last_sent {
+            ready = ready.max(last + global_interval);
+        }
+        if let Some(last) = self.last_sent_per_chat.get(chat_id) {
+            ready = ready.max(*last + chat_interval);
+        }
+        tokio::time::sleep_until(ready).await;
+
+        let now = Instant::now();
+        self.last_sent = Some(now);
+        self.last_sent_per_chat.insert(chat_id.to_string(), now);
+    }
+
+    async fn attempt(&self, method: &str, params: &serde_json::Value) -> Attempt {
+        let url = format!("{}/{}", self.base_url, method);
+        let res = match self.client.post(url).json(params).send().await {
+            Ok(res) => res,
+            Err(e) => return Attempt::Transient(e.into()),
+        };
+        let status = res.status();
+        // 带上具体的错误响应体，这能告诉我们到底是哪里错了
+        let mut body: serde_json::Value = res.json().await.unwrap_or_default();
+        if status.is_success() {
+            return Attempt::Sent(body["result"].take());
+        }
+
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            let wait = body["parameters"]["retry_after"].as_u64().unwrap_or(1);
+            return Attempt::RetryAfter(Duration::from_secs(wait));
+        }
+        let error = anyhow!("Status {}: {}", status, body);
+        if status.is_server_error() {
+            Attempt::Transient(error)
+        } else {
+            Attempt::Fatal(error)
+        }
+    }
+}
+
+pub fn build_client(proxy: &ProxyConfig) -> anyhow::Result<reqwest::Client> {
+    let builder = match proxy.mode {
+        ProxyMode::None => reqwest::Client::builder().no_proxy(),
+        // reqwest 默认就会读取 HTTPS_PROXY / ALL_PROXY 等环境变量
+        ProxyMode::System => reqwest::Client::builder(),
+        ProxyMode::Custom => {
+            let url = proxy.proxy_url()?.expect("custom 代理必须有 url");
+            reqwest::Client::builder().proxy(reqwest::Proxy::all(url)?)
+        }
+    };
+    Ok(builder.build()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ThresholdConfig;
+    use crate::fetch::FetchedTransaction;
+    use crate::message::{Explorer, Locale};
+    use crate::sinks::mock_server::MockServer;
+
+    #[tokio::test]
+    async fn honors_retry_after_and_keeps_order() {
+        let server = MockServer::start(vec![
+            (429, r#"{"ok":false,"error_code":429,"description":"Too Many Requests: retry after 0","parameters":{"retry_after":0}}"#),
+            (200, r#"{"ok":true,"result":{"message_id":1}}"#),
+            (200, r#"{"ok":true,"result":{"message_id":2}}"#),
+            (400, r#"{"ok":false,"error_code":400,"description":"Bad Request: chat not found"}"#),
+        ])
+        .await;
+        let config = TelegramConfig {
+            api_url: server.url.clone(),
+            rate_limit: TelegramRateLimit { per_chat_interval_ms: 20, global_per_second: 100, max_retries: 1 },
+            ..Default::default()
+        };
+        let outbox = TelegramOutbox::spawn(&config, "123:abc").unwrap();
+
+        let (first, second) = tokio::join!(outbox.send("-1001", "第一条".to_string()), outbox.send("-1001", "第二条".to_string()));
+        assert_eq!((first.unwrap(), second.unwrap()), (1, 2));
+        let err = outbox.send("-1002", "第三条".to_string()).await.unwrap_err();
+        assert!(err.to_string().contains("chat not found"), "{}", err);
+
+        let texts: Vec<String> = server.requests().iter().map(|r| r.json()["text"].as_str().unwrap().to_string()).collect();
+        assert_eq!(texts, vec!["第一条", "第一条", "第二条", "第三条"]);
+        assert_eq!(server.requests()[0].path, "/bot123:abc/sendMessage");
+    }
+
+    #[tokio::test]
+    async fn gives_up_on_persistent_rate_limit() {
+        let limited = r#"{"ok":false,"error_code":429,"description":"Too Many Requests: retry after 0","parameters":{"retry_after":0}}"#;
+        let server = MockServer::start(vec![(429, limited), (429, limited), (200, r#"{"ok":true,"result":{"message_id":7}}"#)]).await;
+        let config = TelegramConfig {
+            api_url: server.url.clone(),
+            rate_limit: TelegramRateLimit { per_chat_interval_ms: 0, global_per_second: 100, max_retries: 1 },
+            ..Default::default()
+        };
+        let outbox = TelegramOutbox::spawn(&config, "123:abc").unwrap();
+
+        // 429 也计入重试次数，放弃后后面的消息照常发送
+        let err = outbox.send("-1001", "被限流".to_string()).await.unwrap_err();
+        assert!(err.to_string().contains("限流"), "{}", err);
+        assert_eq!(outbox.send("-1002", "下一条".to_string()).await.unwrap(), 7);
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn edits_alert_when_finalized() {
+        let server = MockServer::start(vec![
+            (200, r#"{"ok":true,"result":{"message_id":42}}"#),
+            (200, r#"{"ok":true,"result":true}"#),
+        ])
+        .await;
+        let config = TelegramConfig {
+            api_url: server.url.clone(),
+            rate_limit: TelegramRateLimit { per_chat_interval_ms: 0, ..Default::default() },
+            ..Default::default()
+        };
+        let renderer = renderer(&MessagesConfig::default(), &MessageOptions::default()).unwrap();
+        let sink = TelegramSink::new(TelegramOutbox::spawn(&config, "123:abc").unwrap(), "-1001", renderer);
+
+        let json = include_str!("../../tests/fixtures/legacy_transfer.json");
+        let tx = FetchedTransaction::parse(serde_json::from_str(json).unwrap()).unwrap();
+        let mut event = WhaleEvent::detect(&tx, &ThresholdConfig::default()).unwrap();
+        sink.send(&event).await.unwrap();
+        event.status = TxStatus::Finalized;
+        sink.update(&event).await.unwrap();
+        // 已经是最终状态，再更新也不会重复编辑
+        sink.update(&event).await.unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].path, "/bot123:abc/sendMessage");
+        assert!(requests[0].json()["text"].as_str().unwrap().contains("🟡 已确认"));
+        assert_eq!(requests[1].path, "/bot123:abc/editMessageText");
+        assert_eq!(requests[1].json()["message_id"], 42);
+        assert!(requests[1].json()["text"].as_str().unwrap().contains("🟢 已最终确认"));
+    }
+
+    #[tokio::test]
+    async fn pending_edits_are_bounded() {
+        let config = TelegramConfig::default();
+        let renderer = renderer(&MessagesConfig::default(), &MessageOptions::default()).unwrap();
+        let sink = TelegramSink::new(TelegramOutbox::spawn(&config, "123:abc").unwrap(), "-1001", renderer);
+
+        for i in 0..=MAX_PENDING {
+            sink.remember(&format!("sig{}", i), i as i64);
+        }
+        let pending = sink.pending.lock().unwrap();
+        assert_eq!(pending.len(), MAX_PENDING);
+        assert!(!pending.contains_key("sig0"));
+        assert!(pending.contains_key(&format!("sig{}", MAX_PENDING)));
+    }
+
+    #[test]
+    fn default_template_matches_legacy_format() {
+        let json = include_str!("../../tests/fixtures/legacy_transfer.json");
+        let tx = FetchedTransaction::parse(serde_json::from_str(json).unwrap()).unwrap();
+        let event = WhaleEvent::detect(&tx, &ThresholdConfig::default()).unwrap();
+
+        let zh = renderer(&MessagesConfig::default(), &MessageOptions::default()).unwrap();
+        assert_eq!(
+            zh.render(&event).unwrap().unwrap(),
+            format!(
+                "🐋 <b>巨鲸警报!</b> 🟡 已确认\n\n💰 <b>金额:</b> 250.00 SOL\n🔀 <b>资金流向:</b>\n  • <code>4Ypn…Jsx2</code> → <code>DTKn…5Tzz</code>: 250.00 SOL\n⛽ 手续费: 0.000005 SOL (付款人 <code>4Ypn…Jsx2</code>)\n🔗 <a href=\"https://solscan.io/tx/{}\">查看交易详情</a>\n📦 Slot: 287654321",
+                event.signature
+            )
+        );
+
+        let options = MessageOptions { locale: Some(Locale::En), explorer: Some(Explorer::SolanaFm), ..Default::default() };
+        let en = renderer(&MessagesConfig::default(), &options).unwrap().render(&event).unwrap().unwrap();
+        assert!(en.starts_with("🐋 <b>Whale Alert!</b> 🟡 Confirmed"), "{}", en);
+        assert!(en.contains(&format!("<a href=\"https://solana.fm/tx/{}\">View transaction</a>", event.signature)));
+    }
+}
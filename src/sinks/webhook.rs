@@ -0,0 +1,378 @@
+use super::AlertSink;
+use crate::backoff::Backoff;
+use crate::config::{Config, SinkConfig, WebhookSinkConfig};
+use crate::event::{Severity, WhaleEvent};
+use anyhow::{anyhow, bail};
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tracing::{info, warn};
+
+// 结构有不兼容的改动时加一，下游按这个字段分版本解析
+pub const SCHEMA_VERSION: u32 = 1;
+pub const SIGNATURE_HEADER: &str = "X-Whale-Signature";
+pub const SCHEMA_VERSION_HEADER: &str = "X-Whale-Schema-Version";
+// 对方要求等太久时也只等这么久，剩下的交给重试次数
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+// --- 对外的事件结构：字段只增不改，和内部的 WhaleEvent 解耦 ---
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Payload {
+    pub schema_version: u32,
+    pub signature: String,
+    pub feed: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub severity: Severity,
+    pub fee_lamports: u64,
+    pub fee_payer: String,
+    pub sol: Option<SolPayload>,
+    pub tokens: Vec<TokenPayload>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SolPayload {
+    pub lamports: u64,
+    pub transfers: Vec<SolTransferPayload>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SolTransferPayload {
+    pub from: String,
+    pub to: String,
+    pub lamports: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TokenPayload {
+    pub mint: String,
+    pub symbol: Option<String>,
+    // UI 金额，已经除过 decimals
+    pub amount: f64,
+    pub transfers: Vec<TokenTransferPayload>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TokenTransferPayload {
+    pub from: String,
+    pub to: String,
+    pub decimals: u8,
+    // 原始数量可能超过 JSON 数字的安全范围，用字符串
+    pub raw_amount: String,
+    pub amount: f64,
+}
+
+impl Payload {
+    pub fn from_event(event: &WhaleEvent) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            signature: event.signature.clone(),
+            feed: event.feed.clone(),
+            slot: event.slot,
+            block_time: event.block_time,
+            severity: event.severity,
+            fee_lamports: event.fee,
+            fee_payer: event.fee_payer.to_string(),
+            sol: event.sol.as_ref().map(|sol| SolPayload {
+                lamports: sol.lamports,
+                transfers: sol
+                    .transfers
+                    .iter()
+                    .map(|t| SolTransferPayload { from: t.from.to_string(), to: t.to.to_string(), lamports: t.lamports })
+                    .collect(),
+            }),
+            tokens: event
+                .tokens
+                .iter()
+                .map(|token| TokenPayload {
+                    mint: token.mint.clone(),
+                    symbol: token.symbol.clone(),
+                    amount: token.amount,
+                    transfers: token
+                        .transfers
+                        .iter()
+                        .map(|t| TokenTransferPayload {
+                            from: t.from.to_string(),
+                            to: t.to.to_string(),
+                            decimals: t.decimals,
+                            raw_amount: t.amount.to_string(),
+                            amount: t.ui_amount(),
+                        })
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+// 死信文件里的一行
+#[derive(Debug, Serialize, Deserialize)]
+struct DeadLetter {
+    failed_at: String,
+    error: String,
+    payload: serde_json::Value,
+}
+
+// --- 通用 webhook 报警通道 ---
+pub struct WebhookSink {
+    client: reqwest::Client,
+    config: WebhookSinkConfig,
+}
+
+impl WebhookSink {
+    pub fn new(config: &WebhookSinkConfig) -> anyhow::Result<Self> {
+        Ok(Self {
+            client: reqwest::Client::builder().timeout(Duration::from_secs(10)).build()?,
+            config: config.clone(),
+        })
+    }
+
+    // 5xx、429 和网络错误按指数退避重试 (429 优先按 Retry-After 等)，其余错误直接放弃
+    async fn deliver(&self, body: &[u8]) -> anyhow::Result<()> {
+        let delay = Duration::from_millis(self.config.retry_delay_ms);
+        let mut backoff = Backoff::new(delay, delay * 32);
+        let mut attempt = 0;
+
+        loop {
+            let (error, retry_after) = match self.post(body).await {
+                Ok(res) if res.status().is_success() => return Ok(()),
+                Ok(res) if res.status() == reqwest::StatusCode::TOO_MANY_REQUESTS => {
+                    (anyhow!("Status {}", res.status()), retry_after(&res))
+                }
+                Ok(res) if res.status().is_server_error() => (anyhow!("Status {}", res.status()), None),
+                Ok(res) => {
+                    let status = res.status();
+                    let text = res.text().await.unwrap_or_default();
+                    bail!("Status {}: {}", status, text);
+                }
+                Err(e) => (e.into(), None),
+            };
+
+            if attempt >= self.config.max_retries {
+                return Err(error.context(format!("重试 {} 次后仍然失败", attempt)));
+            }
+            attempt += 1;
+            let delay = backoff.next_delay();
+            let wait = retry_after.map_or(delay, |wait| wait.min(MAX_RETRY_AFTER));
+            warn!(attempt, wait_secs = wait.as_secs_f64(), error = format!("{:#}", error), "⚠️ Webhook 发送失败，稍后重试");
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    async fn post(&self, body: &[u8]) -> reqwest::Result<reqwest::Response> {
+        let mut request = self
+            .client
+            .post(&self.config.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(SCHEMA_VERSION_HEADER, SCHEMA_VERSION.to_string())
+            .body(body.to_vec());
+        if let Some(secret) = &self.config.secret {
+            request = request.header(SIGNATURE_HEADER, sign(secret, body));
+        }
+        request.send().await
+    }
+
+    async fn park(&self, payload: serde_json::Value, error: &anyhow::Error) -> anyhow::Result<()> {
+        let letter = DeadLetter {
+            failed_at: chrono::Utc::now().to_rfc3339(),
+            error: format!("{:#}", error),
+            payload,
+        };
+        append(&self.config.dead_letter, &serde_json::to_string(&letter)?).await
+    }
+
+    // 把死信重新发一遍。先把死信文件改名再读，重放期间正在运行的 watcher 追加的死信写进新文件，不会丢；
+    // 仍然失败的、解析不了的行都追加回死信文件
+    pub async fn replay(&self) -> anyhow::Result<ReplayStats> {
+        let path: &Path = &self.config.dead_letter;
+        let replaying = replaying_path(path);
+        // 上次重放中途退出留下的文件先接着处理
+        if !tokio::fs::try_exists(&replaying).await? {
+            match tokio::fs::rename(path, &replaying).await {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(ReplayStats::default()),
+                Err(e) => return Err(e.into()),
+            }
+        }
+        let text = tokio::fs::read_to_string(&replaying).await?;
+
+        let mut stats = ReplayStats::default();
+        for line in text.lines().filter(|l| !l.trim().is_empty()) {
+            let letter: DeadLetter = match serde_json::from_str(line) {
+                Ok(letter) => letter,
+                Err(e) => {
+                    warn!(error = %e, "⚠️ 死信格式错误，原样保留");
+                    append(path, line).await?;
+                    stats.malformed += 1;
+                    continue;
+                }
+            };
+            let body = serde_json::to_vec(&letter.payload)?;
+            match self.deliver(&body).await {
+                Ok(()) => stats.delivered += 1,
+                Err(e) => {
+                    let letter = DeadLetter { error: format!("{:#}", e), ..letter };
+                    append(path, &serde_json::to_string(&letter)?).await?;
+                    stats.failed += 1;
+                }
+            }
+        }
+
+        tokio::fs::remove_file(&replaying).await?;
+        Ok(stats)
+    }
+}
+
+#[async_trait]
+impl AlertSink for WebhookSink {
+    async fn send(&self, event: &WhaleEvent) -> anyhow::Result<()> {
+        let payload = serde_json::to_value(Payload::from_event(event))?;
+        let body = serde_json::to_vec(&payload)?;
+
+        if let Err(e) = self.deliver(&body).await {
+            if let Err(park_err) = self.park(payload, &e).await {
+                return Err(e.context(format!("写入死信文件也失败了: {}", park_err)));
+            }
+            return Err(e.context(format!("已写入死信文件 {}", self.config.dead_letter.display())));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ReplayStats {
+    pub delivered: usize,
+    // 仍然失败，写回了死信文件
+    pub failed: usize,
+    // 解析不了的行，原样写回了死信文件
+    pub malformed: usize,
+}
+
+// Retry-After 只支持秒数，HTTP 日期格式的按普通退避处理
+fn retry_after(res: &reqwest::Response) -> Option<Duration> {
+    let secs = res.headers().get(reqwest::header::RETRY_AFTER)?.to_str().ok()?.parse::<f64>().ok()?;
+    Some(Duration::from_secs_f64(secs.max(0.0)))
+}
+
+// 重放时死信文件先改名成这个
+fn replaying_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".replaying");
+    PathBuf::from(name)
+}
+
+// 追加一行；O_APPEND 下整行一次写入，和其他进程同时追加也不会交错
+async fn append(path: &Path, line: &str) -> anyhow::Result<()> {
+    let mut file = tokio::fs::OpenOptions::new().create(true).append(true).open(path).await?;
+    file.write_all(format!("{}\n", line).as_bytes()).await?;
+    // tokio 的 File 写入是在后台线程完成的，不 flush 的话返回时可能还没落盘
+    file.flush().await?;
+    Ok(())
+}
+
+// 签名头的格式: sha256=<hex(HMAC-SHA256(secret, body))>
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC 接受任意长度的密钥");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+// `sol-whale-watcher replay-dead-letters`：重放所有 webhook 通道的死信
+pub async fn replay_all(config: &Config) -> anyhow::Result<()> {
+    for sink in &config.sinks {
+        let SinkConfig::Webhook(w) = sink else { continue };
+        let stats = WebhookSink::new(w)?.replay().await?;
+        info!(
+            sink = w.name.as_deref().unwrap_or(&w.url),
+            delivered = stats.delivered,
+            failed = stats.failed,
+            malformed = stats.malformed,
+            dead_letter = %w.dead_letter.display(),
+            "📮 死信重放完成"
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{SinkFilter, ThresholdConfig};
+    use crate::fetch::FetchedTransaction;
+    use crate::sinks::mock_server::MockServer;
+    use std::path::PathBuf;
+
+    fn legacy_event() -> WhaleEvent {
+        let json = include_str!("../../tests/fixtures/legacy_transfer.json");
+        let tx = FetchedTransaction::parse(serde_json::from_str(json).unwrap()).unwrap();
+        WhaleEvent::detect(&tx, &ThresholdConfig::default()).unwrap()
+    }
+
+    fn sink(url: &str, dead_letter: PathBuf) -> WebhookSink {
+        WebhookSink::new(&WebhookSinkConfig {
+            name: None,
+            url: format!("{}/hooks/whale", url),
+            secret: Some("topsecret".to_string()),
+            secret_env: None,
+            max_retries: 2,
+            retry_delay_ms: 10,
+            dead_letter,
+            filter: SinkFilter::default(),
+        })
+        .unwrap()
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("whale-{}-{}.jsonl", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[tokio::test]
+    async fn signs_and_retries_on_5xx_and_429() {
+        let server = MockServer::start(vec![(429, ""), (503, ""), (200, "{}")]).await;
+        let dead_letter = temp_path("retry");
+        sink(&server.url, dead_letter.clone()).send(&legacy_event()).await.unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        let request = &requests[2];
+        assert_eq!(request.header(SCHEMA_VERSION_HEADER), Some("1"));
+        assert_eq!(request.header(SIGNATURE_HEADER), Some(sign("topsecret", request.body.as_bytes()).as_str()));
+
+        let payload: Payload = serde_json::from_str(&request.body).unwrap();
+        assert_eq!(payload.schema_version, SCHEMA_VERSION);
+        assert_eq!(payload.severity, Severity::Critical);
+        assert_eq!(payload.sol.unwrap().lamports, 250_000_000_000);
+        assert!(!dead_letter.exists());
+    }
+
+    #[tokio::test]
+    async fn parks_and_replays_dead_letters() {
+        let dead_letter = temp_path("park");
+
+        // 4xx 不重试，直接进死信
+        let server = MockServer::start(vec![(400, "bad request")]).await;
+        let err = sink(&server.url, dead_letter.clone()).send(&legacy_event()).await.unwrap_err();
+        assert!(format!("{:#}", err).contains("死信"), "{:#}", err);
+        assert_eq!(server.requests().len(), 1);
+        assert_eq!(std::fs::read_to_string(&dead_letter).unwrap().lines().count(), 1);
+
+        // 坏掉的一行不影响其他死信，原样留在文件里
+        append(&dead_letter, "{not json").await.unwrap();
+
+        let server = MockServer::start(vec![(200, "{}")]).await;
+        let stats = sink(&server.url, dead_letter.clone()).replay().await.unwrap();
+        assert_eq!(stats, ReplayStats { delivered: 1, failed: 0, malformed: 1 });
+        assert_eq!(std::fs::read_to_string(&dead_letter).unwrap(), "{not json\n");
+        assert!(!replaying_path(&dead_letter).exists());
+
+        let payload: Payload = serde_json::from_value(server.requests()[0].json()).unwrap();
+        assert_eq!(payload.signature, legacy_event().signature);
+        let _ = std::fs::remove_file(&dead_letter);
+    }
+}
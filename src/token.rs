@@ -0,0 +1,116 @@
+use crate::balance::pair_flows;
+use crate::fetch::FetchedTransaction;
+use solana_sdk::pubkey::Pubkey;
+use solana_transaction_status::option_serializer::OptionSerializer;
+use solana_transaction_status::UiTransactionTokenBalance;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+// 配对出来的一笔代币流向，from/to 是代币账户的 owner 而不是 ATA 本身
+#[derive(Clone, Debug, PartialEq)]
+pub struct TokenTransfer {
+    pub mint: String,
+    pub decimals: u8,
+    pub from: Pubkey,
+    pub to: Pubkey,
+    pub amount: u128,
+}
+
+impl TokenTransfer {
+    pub fn ui_amount(&self) -> f64 {
+        to_ui_amount(self.amount, self.decimals)
+    }
+}
+
+pub fn to_ui_amount(amount: u128, decimals: u8) -> f64 {
+    amount as f64 / 10f64.powi(decimals as i32)
+}
+
+// 按 (mint, owner) 汇总 pre/post 代币余额的差值，再按 mint 分别配对
+pub fn token_transfers(tx: &FetchedTransaction) -> Vec<TokenTransfer> {
+    let mut deltas: BTreeMap<(String, Pubkey), (u8, i128)> = BTreeMap::new();
+
+    let mut apply = |balances: &OptionSerializer<Vec<UiTransactionTokenBalance>>, sign: i128| {
+        let OptionSerializer::Some(balances) = balances else { return };
+        for balance in balances {
+            let Some(owner) = token_owner(tx, balance) else { continue };
+            let Ok(amount) = balance.ui_token_amount.amount.parse::<u64>() else { continue };
+            let entry = deltas
+                .entry((balance.mint.clone(), owner))
+                .or_insert((balance.ui_token_amount.decimals, 0));
+            entry.1 += sign * amount as i128;
+        }
+    };
+    apply(&tx.meta.pre_token_balances, -1);
+    apply(&tx.meta.post_token_balances, 1);
+
+    let mut by_mint: BTreeMap<String, (u8, Vec<(Pubkey, i128)>)> = BTreeMap::new();
+    for ((mint, owner), (decimals, delta)) in deltas {
+        by_mint.entry(mint).or_insert((decimals, Vec::new())).1.push((owner, delta));
+    }
+
+    by_mint
+        .into_iter()
+        .flat_map(|(mint, (decimals, owners))| {
+            pair_flows(owners).into_iter().map(move |(from, to, amount)| TokenTransfer {
+                mint: mint.clone(),
+                decimals,
+                from,
+                to,
+                amount,
+            })
+        })
+        .collect()
+}
+
+// 老交易没有 owner 字段时退回到代币账户地址
+fn token_owner(tx: &FetchedTransaction, balance: &UiTransactionTokenBalance) -> Option<Pubkey> {
+    match balance.owner.as_ref() {
+        OptionSerializer::Some(owner) => Pubkey::from_str(owner).ok(),
+        _ => tx.account_keys.get(balance.account_index as usize).copied(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USDC: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+
+    fn token_balance(index: u8, owner: &str, amount: u64) -> serde_json::Value {
+        serde_json::json!({
+            "accountIndex": index,
+            "mint": USDC,
+            "owner": owner,
+            "programId": "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
+            "uiTokenAmount": {
+                "amount": amount.to_string(),
+                "decimals": 6,
+                "uiAmount": to_ui_amount(amount as u128, 6),
+                "uiAmountString": to_ui_amount(amount as u128, 6).to_string(),
+            }
+        })
+    }
+
+    #[test]
+    fn usdc_transfer_is_keyed_by_owner() {
+        let sender = "4Ypn3UXUUstkpPhJ7ZpyxACjThWPNyKPMwcn6FdeJsx2";
+        let receiver = "DTKntNWAAidsxiD8QkMscLbGJxTEayn6ecLaVt635Tzz";
+        let mut json: serde_json::Value =
+            serde_json::from_str(include_str!("../tests/fixtures/legacy_transfer.json")).unwrap();
+        json["meta"]["preTokenBalances"] = serde_json::json!([token_balance(1, sender, 80_000_000_000)]);
+        json["meta"]["postTokenBalances"] = serde_json::json!([
+            token_balance(1, sender, 5_000_000_000),
+            // 收款方的 ATA 是这笔交易里新建的，pre 里没有
+            token_balance(2, receiver, 75_000_000_000),
+        ]);
+        let tx = FetchedTransaction::parse(serde_json::from_value(json).unwrap()).unwrap();
+
+        let transfers = token_transfers(&tx);
+        assert_eq!(transfers.len(), 1);
+        assert_eq!(transfers[0].mint, USDC);
+        assert_eq!(transfers[0].from, Pubkey::from_str(sender).unwrap());
+        assert_eq!(transfers[0].to, Pubkey::from_str(receiver).unwrap());
+        assert_eq!(transfers[0].ui_amount(), 75_000.0);
+    }
+}
@@ -0,0 +1,145 @@
+use crate::config::{Config, ThresholdConfig, WalletThreshold};
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use tokio::sync::watch;
+
+// 运行中可以修改的状态：bot 命令改这里，改完立刻落盘
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatchState {
+    pub wallets: Vec<WalletThreshold>,
+    // 覆盖配置文件里的 thresholds.sol
+    pub sol_threshold: Option<f64>,
+    // unix 时间戳 (秒)，在这之前不发报警
+    pub muted_until: Option<i64>,
+}
+
+// --- 关注列表：配置文件里的阈值 + bot 命令修改的状态 ---
+pub struct Watchlist {
+    path: PathBuf,
+    base: ThresholdConfig,
+    state: RwLock<WatchState>,
+    // 串行化修改：写文件期间只挡住其他修改，不挡读
+    writer: tokio::sync::Mutex<()>,
+    // 关注的钱包变了就通知 feed 重新订阅
+    changed: watch::Sender<()>,
+}
+
+impl Watchlist {
+    pub fn load(config: &Config) -> anyhow::Result<Self> {
+        let path = config.watchlist.path.clone();
+        let state = match std::fs::read_to_string(&path) {
+            Ok(text) => serde_json::from_str(&text).with_context(|| format!("关注列表文件 {} 格式错误", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => WatchState::default(),
+            Err(e) => return Err(e).with_context(|| format!("无法读取关注列表文件 {}", path.display())),
+        };
+        Ok(Self::new(path, config.thresholds.clone(), state))
+    }
+
+    fn new(path: PathBuf, base: ThresholdConfig, state: WatchState) -> Self {
+        let (changed, _) = watch::channel(());
+        Self { path, base, state: RwLock::new(state), writer: tokio::sync::Mutex::new(()), changed }
+    }
+
+    pub fn snapshot(&self) -> WatchState {
+        self.state.read().unwrap().clone()
+    }
+
+    // 检测时实际使用的阈值
+    pub fn thresholds(&self) -> ThresholdConfig {
+        let state = self.state.read().unwrap();
+        let mut thresholds = self.base.clone();
+        if let Some(sol) = state.sol_threshold {
+            thresholds.sol = sol;
+        }
+        for wallet in &state.wallets {
+            thresholds.wallets.retain(|w| w.address != wallet.address);
+            thresholds.wallets.push(wallet.clone());
+        }
+        thresholds
+    }
+
+    // 需要单独订阅的钱包 (配置文件 + bot 添加的)
+    pub fn wallets(&self) -> Vec<Pubkey> {
+        self.thresholds().wallets.iter().map(|w| w.address).collect()
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.state.read().unwrap().muted_until.is_some_and(|until| until > chrono::Utc::now().timestamp())
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<()> {
+        self.changed.subscribe()
+    }
+
+    // 修改状态并保存，写文件失败时内存里的修改也不生效。
+    // 写文件时不持有 state 的锁，检测线程读阈值不会被磁盘 IO 卡住
+    pub async fn update<T>(&self, f: impl FnOnce(&mut WatchState) -> T) -> anyhow::Result<T> {
+        let _writer = self.writer.lock().await;
+        let current = self.snapshot();
+        let mut next = current.clone();
+        let result = f(&mut next);
+        if next == current {
+            return Ok(result);
+        }
+
+        let (path, saved) = (self.path.clone(), next.clone());
+        tokio::task::spawn_blocking(move || save(&path, &saved)).await??;
+        let wallets_changed = next.wallets != current.wallets;
+        *self.state.write().unwrap() = next;
+
+        if wallets_changed {
+            self.changed.send_replace(());
+        }
+        Ok(result)
+    }
+}
+
+// 先写临时文件再改名，避免写到一半崩溃把文件弄坏
+fn save(path: &Path, state: &WatchState) -> anyhow::Result<()> {
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, serde_json::to_vec_pretty(state)?)
+        .with_context(|| format!("无法写入关注列表文件 {}", tmp.display()))?;
+    std::fs::rename(&tmp, path).with_context(|| format!("无法写入关注列表文件 {}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[tokio::test]
+    async fn update_persists_and_notifies() {
+        let path = std::env::temp_dir().join(format!("whale-watchlist-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let wallet = Pubkey::from_str("DTKntNWAAidsxiD8QkMscLbGJxTEayn6ecLaVt635Tzz").unwrap();
+
+        let watchlist = Watchlist::new(path.clone(), ThresholdConfig::default(), WatchState::default());
+        let mut changes = watchlist.subscribe();
+        watchlist
+            .update(|s| {
+                s.wallets.push(WalletThreshold { address: wallet, min_sol: 50.0 });
+                s.sol_threshold = Some(1000.0);
+            })
+            .await
+            .unwrap();
+        assert!(changes.has_changed().unwrap());
+
+        let thresholds = watchlist.thresholds();
+        assert_eq!(thresholds.sol, 1000.0);
+        assert_eq!(thresholds.wallet(&wallet).unwrap().min_sol, 50.0);
+
+        // 只改阈值不用重新订阅
+        changes.mark_unchanged();
+        watchlist.update(|s| s.sol_threshold = None).await.unwrap();
+        assert!(!changes.has_changed().unwrap());
+
+        let saved: WatchState = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved, watchlist.snapshot());
+        let _ = std::fs::remove_file(&path);
+    }
+}